@@ -0,0 +1,107 @@
+use crate::{
+    interval::{self, Interval},
+    ray::Ray,
+    vec3::Point3,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+pub const EMPTY: Aabb = Aabb {
+    x: interval::EMPTY,
+    y: interval::EMPTY,
+    z: interval::EMPTY,
+};
+
+impl Aabb {
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        let mut ret = Self { x, y, z };
+        ret.pad_to_minimums();
+        ret
+    }
+
+    // Treat the two points as extrema of the box, in any order.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        let x = Interval::new(a.x().min(b.x()), a.x().max(b.x()));
+        let y = Interval::new(a.y().min(b.y()), a.y().max(b.y()));
+        let z = Interval::new(a.z().min(b.z()), a.z().max(b.z()));
+        Self::new(x, y, z)
+    }
+
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        Self {
+            x: Interval::enclosing(&a.x, &b.x),
+            y: Interval::enclosing(&a.y, &b.y),
+            z: Interval::enclosing(&a.z, &b.z),
+        }
+    }
+
+    pub fn axis_interval(&self, axis: usize) -> &Interval {
+        match axis {
+            1 => &self.y,
+            2 => &self.z,
+            _ => &self.x,
+        }
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        if x > y {
+            if x > z {
+                0
+            } else {
+                2
+            }
+        } else if y > z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn centroid(&self, axis: usize) -> f64 {
+        let i = self.axis_interval(axis);
+        0.5 * (i.min + i.max)
+    }
+
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        let origin = r.origin();
+        let direction = r.direction();
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / direction.e[axis];
+
+            let t0 = (ax.min - origin.e[axis]) * adinv;
+            let t1 = (ax.max - origin.e[axis]) * adinv;
+
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            ray_t.min = ray_t.min.max(t0);
+            ray_t.max = ray_t.max.min(t1);
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Flat primitives would otherwise produce a zero-width slab, which the slab test rejects.
+    fn pad_to_minimums(&mut self) {
+        let delta = 0.0001;
+        if self.x.size() < delta {
+            self.x = self.x.expand(delta);
+        }
+        if self.y.size() < delta {
+            self.y = self.y.expand(delta);
+        }
+        if self.z.size() < delta {
+            self.z = self.z.expand(delta);
+        }
+    }
+}
@@ -1,129 +1,235 @@
-use std::{
-    fs::File,
-    io::{BufWriter, Write},
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
 };
 
 use crate::{
-    color::write_color_to_string,
-    hittable::{HitRecord, Hittable, HittableList},
+    environment::Environment,
+    hittable::{HitRecord, Hittable},
+    image::{Image, RenderLayers},
     interval::Interval,
+    material::ScatterRecord,
+    pdf::Pdf,
     ray::Ray,
     utility,
-    vec3::{Color, Point3, Vec3},
+    vec3::{cross, Color, Point3, Vec3},
 };
 
 use rayon::prelude::*;
 
+pub mod builder;
+
+use builder::CameraBuilder;
+
 #[derive(Default)]
+struct LayerSample {
+    color: Color,
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    object_id: u32,
+}
+
+// Selects the first-hit passes produced by Camera::render_layers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aovs {
+    pub depth: bool,
+    pub normal: bool,
+    pub albedo: bool,
+    pub object_id: bool,
+}
+
+impl Aovs {
+    pub fn all() -> Self {
+        Self {
+            depth: true,
+            normal: true,
+            albedo: true,
+            object_id: true,
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.depth || self.normal || self.albedo || self.object_id
+    }
+}
+
+fn most_frequent(ids: &mut [u32]) -> u32 {
+    ids.sort_unstable();
+    ids.chunk_by(|a, b| a == b)
+        .max_by_key(|run| run.len())
+        .map_or(0, |run| run[0])
+}
+
 pub struct Camera {
-    pub aspect_ratio: f64,
-    pub image_width: i32,
-    pub samples_per_pixel: i32,
-    pub max_depth: i32,
-    pub image_height: i32,
-    pub vfov: f64,
+    image_width: i32,
+    image_height: i32,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    defocus_angle: f64,
     pixel_samples_scale: f64,
-    focal_length: f64,
-    viewport_height: f64,
-    viewport_width: f64,
     camera_center: Point3,
-    viewport_u: Vec3,
-    viewport_v: Vec3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    viewport_upper_left: Vec3,
     pixel00_loc: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    shutter_open: f64,
+    shutter_close: f64,
+    environment: Arc<dyn Environment>,
+    lights: Option<Arc<dyn Hittable>>,
 }
 
 impl Camera {
-    pub fn new(
-        image_width: i32,
-        aspect_ratio: f64,
-        samples_per_pixel: i32,
-        max_depth: i32,
-        vfov: f64,
-    ) -> Self {
-        let mut ret = Self::default();
-        ret.image_width = image_width;
-        ret.aspect_ratio = aspect_ratio;
-        ret.samples_per_pixel = samples_per_pixel;
-        ret.max_depth = max_depth;
-        ret.vfov = vfov;
-
-        ret
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+
+    pub fn image_width(&self) -> i32 {
+        self.image_width
+    }
+    pub fn image_height(&self) -> i32 {
+        self.image_height
+    }
+    pub fn samples_per_pixel(&self) -> i32 {
+        self.samples_per_pixel
+    }
+    pub fn max_depth(&self) -> i32 {
+        self.max_depth
     }
     pub fn size_of_image(&self) -> i32 {
         self.image_width * self.image_height
     }
-    pub fn render(&mut self, world: &HittableList) {
-        self.initialize();
-        let file = File::create("output.ppm").unwrap();
-
-        let mut writer = BufWriter::new(file);
-        //let writer = Arc::new(Mutex::new(writer));
-
-        // Write the PPM header once at the start
-        {
-            //let mut writer = writer.lock().unwrap();
-            writeln!(
-                writer,
-                "P3\n{} {}\n255",
-                self.image_width, self.image_height
-            )
-            .unwrap();
+    pub fn render(&self, world: &dyn Hittable) -> Image {
+        let pixels = self.render_pixels(|x, y| {
+            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+            for _ in 0..self.samples_per_pixel {
+                let r = self.get_ray(x, y);
+                pixel_color += self.ray_color(&r, self.max_depth, world, None);
+            }
+            pixel_color * self.pixel_samples_scale
+        });
+
+        Image::from_pixels(
+            self.image_width as usize,
+            self.image_height as usize,
+            pixels,
+        )
+    }
+
+    // Renders the beauty image together with the first-hit passes selected in `aovs`.
+    // Depth, normal and albedo are averaged over the pixel's camera rays; the object id is
+    // the one hit by the most rays.
+    pub fn render_layers(&self, world: &dyn Hittable, aovs: Aovs) -> RenderLayers {
+        let pixels = self.render_pixels(|x, y| {
+            let mut sample = LayerSample::default();
+            let mut depth_sum = 0.0;
+            let mut hits = 0;
+            let mut ids = Vec::new();
+            for _ in 0..self.samples_per_pixel {
+                let r = self.get_ray(x, y);
+                if !aovs.any() {
+                    sample.color += self.ray_color(&r, self.max_depth, world, None);
+                    continue;
+                }
+
+                // The passes come from the same first hit the beauty sample is shaded from.
+                let mut rec = HitRecord::default();
+                let (hit, transmittance) = self.trace(&r, world, &mut rec);
+                if hit {
+                    sample.color +=
+                        transmittance * self.hit_color(&r, &rec, self.max_depth, world, None);
+                    depth_sum += rec.t * r.direction().length();
+                    hits += 1;
+                    sample.normal += rec.normal;
+                    if aovs.albedo {
+                        sample.albedo += rec.mat.albedo(&rec);
+                    }
+                } else {
+                    sample.color += transmittance * self.miss_color(&r, None);
+                }
+                ids.push(rec.object_id);
+            }
+            sample.color *= self.pixel_samples_scale;
+            sample.albedo *= self.pixel_samples_scale;
+            // Averaging shortens the normals where the surface curves within the pixel.
+            if !sample.normal.near_zero() {
+                sample.normal = Vec3::unit_vector(&sample.normal);
+            }
+            sample.depth = if hits > 0 {
+                depth_sum / hits as f64
+            } else {
+                f64::INFINITY
+            };
+            sample.object_id = most_frequent(&mut ids);
+            sample
+        });
+
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let layer = |f: fn(&LayerSample) -> Color| {
+            Image::from_pixels(width, height, pixels.iter().map(f).collect())
+        };
+        RenderLayers {
+            beauty: layer(|s| s.color),
+            depth: aovs.depth.then(|| {
+                Image::from_pixels(width, height, pixels.iter().map(|s| s.depth).collect())
+            }),
+            normal: aovs.normal.then(|| layer(|s| s.normal)),
+            albedo: aovs.albedo.then(|| layer(|s| s.albedo)),
+            object_id: aovs.object_id.then(|| {
+                Image::from_pixels(width, height, pixels.iter().map(|s| s.object_id).collect())
+            }),
         }
+    }
 
+    // Evaluates `shade` for every pixel in parallel, returning the results row by row.
+    fn render_pixels<T, F>(&self, shade: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(i32, i32) -> T + Sync,
+    {
         let progress = Arc::new(AtomicUsize::new(0));
         let total_rows = self.image_height;
 
-        let rows: Vec<_> = (0..self.image_height)
+        let rows: Vec<Vec<T>> = (0..self.image_height)
             .into_par_iter()
             .map(|y| {
-                let pixel_colors: Vec<_> = (0..self.image_width)
+                let pixels: Vec<_> = (0..self.image_width)
                     .into_par_iter()
-                    .map(|x| {
-                        let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                        for _ in 0..self.samples_per_pixel {
-                            let r = self.get_ray(x, y);
-                            pixel_color += Camera::ray_color(&r, self.max_depth, world);
-                        }
-                        pixel_color * self.pixel_samples_scale
-                    })
+                    .map(|x| shade(x, y))
                     .collect();
 
                 let completed = progress.fetch_add(1, Ordering::Relaxed) + 1;
                 println!("Rendered {}/{} rows", completed, total_rows);
 
-                // Convert row to string after collecting all pixels
-                let mut pixel_str = String::with_capacity(self.image_width as usize * 12);
-                for color in pixel_colors {
-                    write_color_to_string(&mut pixel_str, &color);
-                }
-                pixel_str
+                pixels
             })
             .collect();
 
-        // Write rows in correct order
-        //let mut writer = writer.lock().unwrap();
-        for row_data in rows {
-            writeln!(writer, "{}", row_data).unwrap();
-        }
-        writer.flush().unwrap();
+        rows.into_iter().flatten().collect()
     }
+
     fn get_ray(&self, x: i32, y: i32) -> Ray {
         let offset = self.sample_square();
         let sample_point = self.pixel00_loc
             + (self.pixel_delta_v * (y as f64 + offset.y()))
             + (self.pixel_delta_u * (x as f64 + offset.x()));
 
-        let ray_origin = self.camera_center;
+        let ray_origin = if self.defocus_angle <= 0.0 {
+            self.camera_center
+        } else {
+            self.defocus_disk_sample()
+        };
         let ray_direction = sample_point - ray_origin;
+        let ray_time =
+            self.shutter_open + (self.shutter_close - self.shutter_open) * utility::random_double();
+
+        Ray::with_time(ray_origin, ray_direction, ray_time)
+    }
 
-        Ray::new(ray_origin, ray_direction)
+    fn defocus_disk_sample(&self) -> Point3 {
+        let p = Vec3::random_in_unit_disk();
+        self.camera_center + (self.defocus_disk_u * p.x()) + (self.defocus_disk_v * p.y())
     }
 
     fn sample_square(&self) -> Vec3 {
@@ -131,53 +237,190 @@ impl Camera {
         let y_off = utility::random_double() - 0.5;
         Vec3::new(x_off, y_off, 0.0)
     }
-    fn initialize(&mut self) {
-        self.image_height = ((self.image_width as f64 / self.aspect_ratio) as i32).max(1);
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
+    // Expects settings that have already been validated by CameraBuilder::build.
+    fn from_settings(settings: &CameraBuilder) -> Self {
+        let image_width = settings.image_width;
+        let image_height = ((image_width as f64 / settings.aspect_ratio) as i32).max(1);
 
-        self.focal_length = 1.0;
+        let camera_center = settings.lookfrom;
 
-        let theta = utility::degrees_to_radians(self.vfov);
+        let theta = utility::degrees_to_radians(settings.vfov);
         let h = (theta / 2.0).tan();
 
-        self.viewport_height = 2.0 * h * self.focal_length;
-        self.viewport_width =
-            self.viewport_height * (self.image_width as f64 / self.image_height as f64);
-        self.camera_center = Point3::new(0.0, 0.0, 0.0);
+        let viewport_height = 2.0 * h * settings.focus_dist;
+        let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
 
-        self.viewport_u = Vec3::new(self.viewport_width, 0.0, 0.0);
-        self.viewport_v = Vec3::new(0.0, -self.viewport_height, 0.0);
+        // Orthonormal basis for the camera frame; the camera looks down -w.
+        let w = Vec3::unit_vector(&(settings.lookfrom - settings.lookat));
+        let u = Vec3::unit_vector(&cross(&settings.vup, &w));
+        let v = cross(&w, &u);
 
-        self.pixel_delta_u = self.viewport_u / self.image_width as f64;
-        self.pixel_delta_v = self.viewport_v / self.image_height as f64;
+        let viewport_u = u * viewport_width;
+        let viewport_v = -v * viewport_height;
 
-        self.viewport_upper_left = self.camera_center
-            - Vec3::new(0.0, 0.0, self.focal_length)
-            - self.viewport_u / 2.0
-            - self.viewport_v / 2.0;
-        self.pixel00_loc =
-            self.viewport_upper_left + (self.pixel_delta_u + self.pixel_delta_v) * 0.5;
+        let pixel_delta_u = viewport_u / image_width as f64;
+        let pixel_delta_v = viewport_v / image_height as f64;
+
+        let viewport_upper_left =
+            camera_center - w * settings.focus_dist - viewport_u / 2.0 - viewport_v / 2.0;
+        let pixel00_loc = viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5;
+
+        // The lens is a disk at the camera center whose radius subtends defocus_angle
+        // at the focus plane.
+        let defocus_radius =
+            settings.focus_dist * utility::degrees_to_radians(settings.defocus_angle / 2.0).tan();
+
+        Self {
+            image_width,
+            image_height,
+            samples_per_pixel: settings.samples_per_pixel,
+            max_depth: settings.max_depth,
+            defocus_angle: settings.defocus_angle,
+            pixel_samples_scale: 1.0 / settings.samples_per_pixel as f64,
+            camera_center,
+            pixel_delta_u,
+            pixel_delta_v,
+            pixel00_loc,
+            defocus_disk_u: u * defocus_radius,
+            defocus_disk_v: v * defocus_radius,
+            shutter_open: settings.shutter_open,
+            shutter_close: settings.shutter_close,
+            environment: settings.environment.clone(),
+            lights: settings.lights.clone(),
+        }
     }
 
-    fn ray_color(r: &Ray, depth: i32, world: &HittableList) -> Color {
+    // `scatter_pdf` is the density with which the previous bounce picked this ray, or
+    // None for camera rays and specular bounces, which light sampling can't reproduce.
+    // Light it finds is weighted against the chance of sampling it directly instead.
+    fn ray_color(
+        &self,
+        r: &Ray,
+        depth: i32,
+        world: &dyn Hittable,
+        scatter_pdf: Option<f64>,
+    ) -> Color {
         if depth <= 0 {
             return Color::new(0.0, 0.0, 0.0);
         }
         let mut rec = HitRecord::default();
-        if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-            //let dir = Vec3::random_on_hemisphere(&rec.normal);
-            //let dir = rec.normal + Vec3::random_unit_vector();
+        let (hit, transmittance) = self.trace(r, world, &mut rec);
+        if !hit {
+            return transmittance * self.miss_color(r, scatter_pdf);
+        }
+        transmittance * self.hit_color(r, &rec, depth, world, scatter_pdf)
+    }
+
+    // Finds the nearest hit along `r`, along with the weight of the volumes the ray
+    // crosses on the way there.
+    fn trace(&self, r: &Ray, world: &dyn Hittable, rec: &mut HitRecord) -> (bool, Color) {
+        let hit = world.hit(r, Interval::new(0.001, f64::INFINITY), rec);
+        if !world.has_transmittance() {
+            return (hit, Color::new(1.0, 1.0, 1.0));
+        }
+        let t_max = if hit { rec.t } else { f64::INFINITY };
+        (hit, world.transmittance(r, Interval::new(0.001, t_max)))
+    }
 
-            let mut scattered: Ray = Ray::default();
-            let mut attenuation: Color = Color::default();
+    fn miss_color(&self, r: &Ray, scatter_pdf: Option<f64>) -> Color {
+        let weight = scatter_pdf.map_or(1.0, |pdf| {
+            power_heuristic(pdf, self.environment.pdf(&r.direction()))
+        });
+        self.environment.value(&r.direction()) * weight
+    }
 
-            if rec.mat.scatter(&r, &rec, &mut attenuation, &mut scattered) {
-                return attenuation * Camera::ray_color(&scattered, depth - 1, world);
+    // Light leaving `rec`, the nearest hit along `r`, back toward the ray's origin.
+    fn hit_color(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        depth: i32,
+        world: &dyn Hittable,
+        scatter_pdf: Option<f64>,
+    ) -> Color {
+        let emitted = rec.mat.emitted(rec.u, rec.v, &rec.p);
+        let color_from_emission = match (scatter_pdf, &self.lights) {
+            (Some(pdf), Some(lights)) => {
+                emitted
+                    * power_heuristic(pdf, lights.pdf_value(&r.origin(), &r.direction(), r.time()))
             }
-            return Color::new(0.0, 0.0, 0.0);
+            _ => emitted,
+        };
+
+        let mut srec = ScatterRecord::default();
+        if !rec.mat.scatter(r, rec, &mut srec) {
+            return color_from_emission;
         }
-        let unit_direction = Vec3::unit_vector(&r.direction());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-        Color::new(1.0, 1.0, 1.0) * (1.0 - a) + Color::new(0.5, 0.7, 1.0) * a
+        if srec.skip_pdf {
+            return color_from_emission
+                + srec.attenuation * self.ray_color(&srec.skip_pdf_ray, depth - 1, world, None);
+        }
+
+        // Sampling from the material's density rather than exactly in proportion to its
+        // BSDF is corrected for by the ratio of the two.
+        let scattered = Ray::with_time(rec.p, srec.pdf.generate(), r.time());
+        let pdf = srec.pdf.value(&scattered.direction());
+        let scattering_pdf = rec.mat.scattering_pdf(r, rec, &scattered);
+        let color_from_scatter = if pdf > 0.0 && scattering_pdf > 0.0 {
+            self.ray_color(&scattered, depth - 1, world, Some(pdf)) * (scattering_pdf / pdf)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+        let color_from_lights = self.sample_lights(r, rec, &srec.pdf, world);
+        color_from_emission + srec.attenuation * (color_from_lights + color_from_scatter)
+    }
+
+    // Next-event estimation: one shadow ray toward the lights and one toward the
+    // environment, each weighed against the chance that `scatter_pdf` picks the same
+    // direction. The result is still to be multiplied by the scatter attenuation.
+    fn sample_lights(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        scatter_pdf: &dyn Pdf,
+        world: &dyn Hittable,
+    ) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+
+        if let Some(lights) = &self.lights {
+            let ray = Ray::with_time(rec.p, lights.random(&rec.p, r_in.time()), r_in.time());
+            let pdf = lights.pdf_value(&rec.p, &ray.direction(), r_in.time());
+            let scattering_pdf = rec.mat.scattering_pdf(r_in, rec, &ray);
+            let mut light_rec = HitRecord::default();
+            if pdf > 0.0 && scattering_pdf > 0.0 {
+                let (hit, transmittance) = self.trace(&ray, world, &mut light_rec);
+                if hit {
+                    let emitted = light_rec
+                        .mat
+                        .emitted(light_rec.u, light_rec.v, &light_rec.p);
+                    let weight = power_heuristic(pdf, scatter_pdf.value(&ray.direction()));
+                    color += transmittance * emitted * (scattering_pdf * weight / pdf);
+                }
+            }
+        }
+
+        let ray = Ray::with_time(rec.p, self.environment.sample(), r_in.time());
+        let pdf = self.environment.pdf(&ray.direction());
+        let radiance = self.environment.value(&ray.direction());
+        let scattering_pdf = rec.mat.scattering_pdf(r_in, rec, &ray);
+        if pdf > 0.0 && scattering_pdf > 0.0 && !radiance.near_zero() {
+            let (hit, transmittance) = self.trace(&ray, world, &mut HitRecord::default());
+            if !hit {
+                let weight = power_heuristic(pdf, scatter_pdf.value(&ray.direction()));
+                color += transmittance * radiance * (scattering_pdf * weight / pdf);
+            }
+        }
+
+        color
+    }
+}
+
+// Multiple importance sampling weight for a sample drawn with density `pdf` when
+// `other` could have produced it too.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0.0 {
+        return 0.0;
     }
+    a / (a + b)
 }
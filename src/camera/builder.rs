@@ -0,0 +1,215 @@
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use crate::{
+    environment::{Environment, GradientEnvironment},
+    hittable::Hittable,
+    vec3::{cross, Point3, Vec3},
+};
+
+use super::Camera;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraError {
+    InvalidImageWidth(i32),
+    InvalidAspectRatio(f64),
+    InvalidSamplesPerPixel(i32),
+    InvalidMaxDepth(i32),
+    InvalidVfov(f64),
+    InvalidDefocusAngle(f64),
+    InvalidFocusDist(f64),
+    InvalidShutter(f64, f64),
+    LookfromEqualsLookat,
+    VupParallelToView,
+}
+
+impl Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidImageWidth(w) => write!(f, "image width must be positive, got {}", w),
+            Self::InvalidAspectRatio(a) => {
+                write!(f, "aspect ratio must be positive and finite, got {}", a)
+            }
+            Self::InvalidSamplesPerPixel(n) => {
+                write!(f, "samples per pixel must be positive, got {}", n)
+            }
+            Self::InvalidMaxDepth(d) => write!(f, "max depth must be positive, got {}", d),
+            Self::InvalidVfov(v) => {
+                write!(f, "vertical fov must be in (0, 180) degrees, got {}", v)
+            }
+            Self::InvalidDefocusAngle(a) => {
+                write!(f, "defocus angle must be in [0, 180) degrees, got {}", a)
+            }
+            Self::InvalidFocusDist(d) => {
+                write!(f, "focus distance must be positive and finite, got {}", d)
+            }
+            Self::InvalidShutter(open, close) => write!(
+                f,
+                "shutter must open before it closes, got [{}, {}]",
+                open, close
+            ),
+            Self::LookfromEqualsLookat => write!(f, "lookfrom and lookat must differ"),
+            Self::VupParallelToView => {
+                write!(f, "vup must not be parallel to the view direction")
+            }
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+#[derive(Clone)]
+pub struct CameraBuilder {
+    pub(super) image_width: i32,
+    pub(super) aspect_ratio: f64,
+    pub(super) samples_per_pixel: i32,
+    pub(super) max_depth: i32,
+    pub(super) vfov: f64,
+    pub(super) lookfrom: Point3,
+    pub(super) lookat: Point3,
+    pub(super) vup: Vec3,
+    pub(super) defocus_angle: f64,
+    pub(super) focus_dist: f64,
+    pub(super) shutter_open: f64,
+    pub(super) shutter_close: f64,
+    pub(super) environment: Arc<dyn Environment>,
+    pub(super) lights: Option<Arc<dyn Hittable>>,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            image_width: 400,
+            aspect_ratio: 16.0 / 9.0,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            vfov: 90.0,
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            environment: Arc::new(GradientEnvironment::default()),
+            lights: None,
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn image_width(mut self, image_width: i32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+    pub fn samples_per_pixel(mut self, samples_per_pixel: i32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+    pub fn max_depth(mut self, max_depth: i32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    // Rays are spread over [open, close]; moving objects travel from time 0 to 1. Equal
+    // values freeze motion at that instant.
+    pub fn shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    pub fn environment(mut self, environment: Arc<dyn Environment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    // Emitters to sample directly at every diffuse bounce, which finds small lights far
+    // more often than random bounces do. They must also be part of the world. Spheres,
+    // quads, triangles and meshes can be sampled, as can lists, BVHs and instances of
+    // them; volumes can't.
+    pub fn lights(mut self, lights: Arc<dyn Hittable>) -> Self {
+        self.lights = Some(lights);
+        self
+    }
+
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        self.validate()?;
+        Ok(Camera::from_settings(self))
+    }
+
+    fn validate(&self) -> Result<(), CameraError> {
+        if self.image_width <= 0 {
+            return Err(CameraError::InvalidImageWidth(self.image_width));
+        }
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
+        }
+        if self.samples_per_pixel <= 0 {
+            return Err(CameraError::InvalidSamplesPerPixel(self.samples_per_pixel));
+        }
+        if self.max_depth <= 0 {
+            return Err(CameraError::InvalidMaxDepth(self.max_depth));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(CameraError::InvalidVfov(self.vfov));
+        }
+        if !(self.defocus_angle >= 0.0 && self.defocus_angle < 180.0) {
+            return Err(CameraError::InvalidDefocusAngle(self.defocus_angle));
+        }
+        if !(self.focus_dist.is_finite() && self.focus_dist > 0.0) {
+            return Err(CameraError::InvalidFocusDist(self.focus_dist));
+        }
+
+        if !(self.shutter_open.is_finite()
+            && self.shutter_close.is_finite()
+            && self.shutter_open <= self.shutter_close)
+        {
+            return Err(CameraError::InvalidShutter(
+                self.shutter_open,
+                self.shutter_close,
+            ));
+        }
+
+        let view = self.lookfrom - self.lookat;
+        if view.near_zero() {
+            return Err(CameraError::LookfromEqualsLookat);
+        }
+        if cross(&self.vup, &view).near_zero() {
+            return Err(CameraError::VupParallelToView);
+        }
+
+        Ok(())
+    }
+}
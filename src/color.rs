@@ -1,5 +1,3 @@
-use std::io::{BufWriter, Write};
-
 use crate::{interval::Interval, vec3::Color};
 
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
@@ -10,6 +8,16 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     }
 }
 
+// Inverse of linear_to_gamma, for reading 8-bit images back into linear color.
+pub fn gamma_to_linear(gamma_component: f64) -> f64 {
+    gamma_component * gamma_component
+}
+
+pub fn bytes_to_color(r: u8, g: u8, b: u8) -> Color {
+    let to_linear = |c: u8| gamma_to_linear(c as f64 / 255.0);
+    Color::new(to_linear(r), to_linear(g), to_linear(b))
+}
+
 pub fn color_to_bytes(color: &Color) -> (i32, i32, i32) {
     let color_interval = Interval::new(0.0, 0.999);
     let r = linear_to_gamma(color.x());
@@ -22,13 +30,9 @@ pub fn color_to_bytes(color: &Color) -> (i32, i32, i32) {
     (rbyte, gbyte, bbyte)
 }
 
-pub fn write_color<W: Write>(w: &mut BufWriter<W>, color: &Color) {
-    let (rbyte, gbyte, bbyte) = color_to_bytes(color);
-    writeln!(w, "{} {} {}\n", rbyte, gbyte, bbyte).unwrap();
-}
-
-pub fn write_color_to_string(buffer: &mut String, color: &Color) {
-    let (rbyte, gbyte, bbyte) = color_to_bytes(color);
+pub fn color_to_words(color: &Color) -> (u16, u16, u16) {
+    let color_interval = Interval::new(0.0, 1.0);
+    let to_word = |c: f64| (color_interval.clamp(linear_to_gamma(c)) * 65535.0).round() as u16;
 
-    buffer.push_str(&format!("{} {} {}\n", rbyte, gbyte, bbyte));
+    (to_word(color.x()), to_word(color.y()), to_word(color.z()))
 }
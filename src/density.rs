@@ -0,0 +1,259 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use crate::{aabb::Aabb, interval::Interval, perlin::Perlin, vec3::Point3};
+
+// The most voxels a grid may have, which keeps a grid's values under 2 GiB.
+const MAX_VOXELS: usize = 1 << 28;
+
+// A spatially varying density for heterogeneous media. `max_density` must bound
+// `density` everywhere, since the free-flight sampling uses it as a majorant.
+pub trait Density: Send + Sync {
+    fn density(&self, p: &Point3) -> f64;
+
+    fn max_density(&self) -> f64;
+}
+
+// Billowy density from Perlin turbulence, clamped to [0, scale].
+pub struct NoiseDensity {
+    noise: Perlin,
+    frequency: f64,
+    scale: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(frequency: f64, scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            frequency,
+            scale,
+        }
+    }
+}
+
+impl Density for NoiseDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        self.scale * self.noise.turb(&(*p * self.frequency), 7).clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.scale
+    }
+}
+
+// Voxel densities spread over a box and interpolated trilinearly, with zero density
+// outside the box. Values are stored x fastest, then y, then z.
+pub struct GridDensity {
+    resolution: [usize; 3],
+    bbox: Aabb,
+    values: Vec<f64>,
+    max: f64,
+}
+
+impl GridDensity {
+    pub fn new(resolution: [usize; 3], bbox: Aabb, values: Vec<f64>) -> io::Result<Self> {
+        let count = voxel_count(resolution).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "grid resolution is empty or too large",
+            )
+        })?;
+        if values.len() != count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "voxel count does not match resolution",
+            ));
+        }
+        let max = values.iter().copied().fold(0.0, f64::max);
+        Ok(Self {
+            resolution,
+            bbox,
+            values,
+            max,
+        })
+    }
+
+    // Reads a Mitsuba `.vol` grid of float32 voxels. Only the first channel of
+    // multi-channel grids is used.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic[..3] != b"VOL" || magic[3] != 3 {
+            return Err(invalid("not a version 3 VOL file"));
+        }
+
+        let mut read_i32 = || -> io::Result<i32> {
+            let mut bytes = [0u8; 4];
+            r.read_exact(&mut bytes)?;
+            Ok(i32::from_le_bytes(bytes))
+        };
+        let encoding = read_i32()?;
+        let dims = [read_i32()?, read_i32()?, read_i32()?];
+        let channels = read_i32()?;
+        if encoding != 1 {
+            return Err(invalid("only float32 VOL grids are supported"));
+        }
+        if dims.iter().any(|&d| d <= 0) || channels <= 0 {
+            return Err(invalid("VOL grid has an empty dimension"));
+        }
+
+        let mut read_f32 = || -> io::Result<f64> {
+            let mut bytes = [0u8; 4];
+            r.read_exact(&mut bytes)?;
+            Ok(f32::from_le_bytes(bytes) as f64)
+        };
+        let min = Point3::new(read_f32()?, read_f32()?, read_f32()?);
+        let max = Point3::new(read_f32()?, read_f32()?, read_f32()?);
+
+        let resolution = dims.map(|d| d as usize);
+        let voxel_size = 4 * channels as usize;
+        let size = voxel_count(resolution)
+            .and_then(|count| count.checked_mul(voxel_size))
+            .ok_or_else(|| invalid("VOL grid is too large"))?;
+
+        // Read what the file holds rather than trusting the header with an allocation.
+        let mut data = Vec::new();
+        r.take(size as u64).read_to_end(&mut data)?;
+        if data.len() != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "VOL grid is truncated",
+            ));
+        }
+        let values = data
+            .chunks_exact(voxel_size)
+            .map(|voxel| f32::from_le_bytes([voxel[0], voxel[1], voxel[2], voxel[3]]) as f64)
+            .collect();
+
+        Self::new(resolution, Aabb::from_points(min, max), values)
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(z * ny + y) * nx + x]
+    }
+}
+
+// The number of voxels at a resolution, if none of its sides is empty and the total
+// is within `MAX_VOXELS`.
+fn voxel_count(resolution: [usize; 3]) -> Option<usize> {
+    let count = resolution
+        .iter()
+        .try_fold(1usize, |count, &n| count.checked_mul(n))?;
+    (count > 0 && count <= MAX_VOXELS).then_some(count)
+}
+
+impl Density for GridDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        let axes = [
+            (&self.bbox.x, p.x()),
+            (&self.bbox.y, p.y()),
+            (&self.bbox.z, p.z()),
+        ];
+        if !axes.iter().all(|(interval, v)| interval.contains(*v)) {
+            return 0.0;
+        }
+
+        // Voxel values sit at cell centers; find the cell below the point on each axis
+        // and the fraction of the way to the next one.
+        let cell = |axis: usize| {
+            let (interval, v): (&Interval, f64) = axes[axis];
+            let n = self.resolution[axis];
+            let g =
+                ((v - interval.min) / interval.size() * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (g.floor() as usize).min(n.saturating_sub(2));
+            let next = (i + 1).min(n - 1);
+            (i, next, g - i as f64)
+        };
+        let (x0, x1, fx) = cell(0);
+        let (y0, y1, fy) = cell(1);
+        let (z0, z1, fz) = cell(2);
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.voxel(x0, y0, z), self.voxel(x1, y0, z), fx),
+                lerp(self.voxel(x0, y1, z), self.voxel(x1, y1, z), fx),
+                fy,
+            )
+        };
+        lerp(plane(z0), plane(z1), fz)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn vol_file(name: &str, dims: [i32; 3], channels: i32, voxels: usize) -> PathBuf {
+        let mut bytes = b"VOL\x03".to_vec();
+        for v in [1, dims[0], dims[1], dims[2], channels] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in 0..voxels * channels as usize {
+            bytes.extend_from_slice(&(i as f32).to_le_bytes());
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("density_{}_{}.vol", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn load(path: PathBuf) -> io::Result<GridDensity> {
+        let grid = GridDensity::load(&path);
+        fs::remove_file(path).unwrap();
+        grid
+    }
+
+    #[test]
+    fn loads_first_channel() {
+        let grid = load(vol_file("valid", [2, 1, 1], 2, 2)).unwrap();
+        assert_eq!(grid.values, vec![0.0, 2.0]);
+        assert_eq!(grid.max_density(), 2.0);
+    }
+
+    #[test]
+    fn rejects_oversized_grids() {
+        for dims in [[i32::MAX, i32::MAX, i32::MAX], [1 << 10, 1 << 10, 1 << 10]] {
+            let err = load(vol_file("oversized", dims, 1, 0)).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_grids() {
+        let err = load(vol_file("truncated", [4, 4, 4], 1, 10)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn rejects_mismatched_values() {
+        let bbox = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        for (resolution, count) in [([2, 2, 2], 7), ([0, 2, 2], 0)] {
+            let err = GridDensity::new(resolution, bbox, vec![0.0; count])
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
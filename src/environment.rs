@@ -0,0 +1,332 @@
+use std::{f64::consts::PI, io, path::Path};
+
+use crate::{
+    image::Image,
+    utility::{self, random_double},
+    vec3::{Color, Vec3},
+};
+
+// Radiance arriving from infinitely far away, for rays that escape the scene.
+pub trait Environment: Send + Sync {
+    fn value(&self, direction: &Vec3) -> Color;
+
+    // Picks a unit direction toward the environment, by default uniformly over the sphere.
+    fn sample(&self) -> Vec3 {
+        Vec3::random_unit_vector()
+    }
+
+    // Solid angle density with which `sample` returns `direction`.
+    fn pdf(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+pub struct ConstantEnvironment {
+    color: Color,
+}
+
+impl ConstantEnvironment {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Environment for ConstantEnvironment {
+    fn value(&self, _direction: &Vec3) -> Color {
+        self.color
+    }
+}
+
+// Blends from `horizon` straight down to `zenith` straight up.
+pub struct GradientEnvironment {
+    horizon: Color,
+    zenith: Color,
+}
+
+impl GradientEnvironment {
+    pub fn new(horizon: Color, zenith: Color) -> Self {
+        Self { horizon, zenith }
+    }
+}
+
+impl Default for GradientEnvironment {
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Environment for GradientEnvironment {
+    fn value(&self, direction: &Vec3) -> Color {
+        let unit_direction = Vec3::unit_vector(direction);
+        let a = 0.5 * (unit_direction.y() + 1.0);
+        self.horizon * (1.0 - a) + self.zenith * a
+    }
+}
+
+// Image based lighting from an equirectangular (latitude-longitude) panorama of linear
+// radiance. The top row of the image is straight up and the bottom row straight down.
+// From left to right the columns face -X, +Z, +X and -Z, so the horizontal center
+// faces +X. A sphere with an `ImageTexture` of the panorama looks up the same texel
+// for each direction from its center. Directions are importance sampled in
+// proportion to the luminance of the panorama, so small bright sources such as the sun
+// are found by sampling instead of by chance.
+pub struct ImageEnvironment {
+    image: Image,
+    intensity: f64,
+    // Rotation of the panorama around +Y, as its sine and cosine.
+    sin_rotation: f64,
+    cos_rotation: f64,
+    distribution: Distribution2D,
+}
+
+impl ImageEnvironment {
+    pub fn new(image: Image) -> Self {
+        let distribution = Distribution2D::new(&image);
+        Self {
+            image,
+            intensity: 1.0,
+            sin_rotation: 0.0,
+            cos_rotation: 1.0,
+            distribution,
+        }
+    }
+
+    // Loads a Radiance .hdr or .pfm panorama.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(Image::load(path)?))
+    }
+
+    // Rotates the panorama by `degrees` around +Y.
+    pub fn with_rotation(mut self, degrees: f64) -> Self {
+        let theta = utility::degrees_to_radians(degrees);
+        self.sin_rotation = theta.sin();
+        self.cos_rotation = theta.cos();
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    fn rotate(&self, d: &Vec3, sin_theta: f64) -> Vec3 {
+        Vec3::new(
+            self.cos_rotation * d.x() + sin_theta * d.z(),
+            d.y(),
+            -sin_theta * d.x() + self.cos_rotation * d.z(),
+        )
+    }
+
+    // Maps a world direction to panorama coordinates.
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = Vec3::unit_vector(&self.rotate(direction, -self.sin_rotation));
+        let u = ((-d.z()).atan2(d.x()) + PI) / (2.0 * PI);
+        let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    // The inverse of `direction_to_uv`, giving a unit direction.
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = u * 2.0 * PI - PI;
+        let theta = v * PI;
+        let d = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            -theta.sin() * phi.sin(),
+        );
+        self.rotate(&d, self.sin_rotation)
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn value(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        self.image.sample(u, v) * self.intensity
+    }
+
+    fn sample(&self) -> Vec3 {
+        let (u, v) = self.distribution.sample(random_double(), random_double());
+        self.uv_to_direction(u, v)
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // Change of variables from the unit square to solid angle.
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+// Piecewise-constant density over [0, 1), sampled by inverting its CDF.
+struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    // Returns a point in [0, 1) along with the index of the segment it falls in.
+    fn sample(&self, u: f64) -> (f64, usize) {
+        let n = self.func.len();
+        let offset = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(n - 1);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        ((offset as f64 + du) / n as f64, offset)
+    }
+
+    fn pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// Density over panorama coordinates proportional to luminance, weighted by the solid
+// angle each row covers.
+struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    fn new(image: &Image) -> Self {
+        if image.pixels().is_empty() {
+            return Self {
+                conditional: vec![Distribution1D::new(vec![1.0])],
+                marginal: Distribution1D::new(vec![1.0]),
+            };
+        }
+
+        let (width, height) = (image.width(), image.height());
+        let luminance = |c: Color| 0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z();
+
+        let average = image
+            .pixels()
+            .iter()
+            .map(|&c| luminance(c).max(0.0))
+            .sum::<f64>()
+            / (width * height) as f64;
+        // Keep every texel reachable: bilinear lookups can give dark texels a little
+        // radiance from their neighbours.
+        let floor = if average > 0.0 { average * 1e-3 } else { 1.0 };
+
+        let conditional: Vec<Distribution1D> = image
+            .rows()
+            .enumerate()
+            .map(|(y, row)| {
+                let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+                let func = row
+                    .iter()
+                    .map(|&c| (luminance(c).max(0.0) + floor) * sin_theta)
+                    .collect();
+                Distribution1D::new(func)
+            })
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral).collect());
+
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    fn sample(&self, u0: f64, u1: f64) -> (f64, f64) {
+        let (v, row) = self.marginal.sample(u1);
+        let (u, _) = self.conditional[row].sample(u0);
+        (u, v)
+    }
+
+    fn pdf(&self, u: f64, v: f64) -> f64 {
+        let rows = self.conditional.len();
+        let row = ((v * rows as f64) as usize).min(rows - 1);
+        let columns = self.conditional[row].func.len();
+        let column = ((u * columns as f64) as usize).min(columns - 1);
+
+        self.marginal.pdf(row) * self.conditional[row].pdf(column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::random_double_range;
+
+    fn panorama() -> ImageEnvironment {
+        ImageEnvironment::new(Image::new(8, 4))
+    }
+
+    fn assert_uv(env: &ImageEnvironment, direction: Vec3, u: f64, v: f64) {
+        let (actual_u, actual_v) = env.direction_to_uv(&direction);
+        assert!(
+            (actual_u - u).abs() < 1e-9 && (actual_v - v).abs() < 1e-9,
+            "{:?} maps to ({}, {}), not ({}, {})",
+            direction,
+            actual_u,
+            actual_v,
+            u,
+            v
+        );
+    }
+
+    #[test]
+    fn maps_axes_to_documented_texels() {
+        let env = panorama();
+        assert_uv(&env, Vec3::new(0.0, 1.0, 0.0), 0.5, 0.0);
+        assert_uv(&env, Vec3::new(0.0, -1.0, 0.0), 0.5, 1.0);
+        assert_uv(&env, Vec3::new(0.0, 0.0, 1.0), 0.25, 0.5);
+        assert_uv(&env, Vec3::new(1.0, 0.0, 0.0), 0.5, 0.5);
+        assert_uv(&env, Vec3::new(0.0, 0.0, -1.0), 0.75, 0.5);
+
+        // Rotating the panorama by 90 degrees brings its center round to -Z.
+        let rotated = panorama().with_rotation(90.0);
+        assert_uv(&rotated, Vec3::new(0.0, 0.0, -1.0), 0.5, 0.5);
+    }
+
+    #[test]
+    fn uv_round_trips() {
+        for env in [panorama(), panorama().with_rotation(37.0)] {
+            for _ in 0..1000 {
+                let (u, v) = (
+                    random_double_range(0.01, 0.99),
+                    random_double_range(0.01, 0.99),
+                );
+                let (actual_u, actual_v) = env.direction_to_uv(&env.uv_to_direction(u, v));
+                assert!((actual_u - u).abs() < 1e-9, "u {} became {}", u, actual_u);
+                assert!((actual_v - v).abs() < 1e-9, "v {} became {}", v, actual_v);
+            }
+        }
+    }
+}
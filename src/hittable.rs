@@ -1,21 +1,39 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
 use crate::{
+    aabb::{self, Aabb},
     interval::Interval,
     material::{Lambertian, Material},
     ray::Ray,
-    vec3::{dot, Point3, Vec3},
+    utility::random_int,
+    vec3::{dot, Color, Point3, Vec3},
 };
 
+pub mod bvh;
+pub mod constant_medium;
+pub mod heterogeneous_medium;
+pub mod instance;
+pub mod mesh;
+pub mod quad;
 pub mod sphere;
+pub mod triangle;
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
     pub mat: Arc<dyn Material>,
+    // Color interpolated from a mesh's vertex colors, which tints the material. Every
+    // hittable sets it, since records are reused across hits.
+    pub vertex_color: Option<Color>,
+    pub object_id: u32,
 }
 
 impl Default for HitRecord {
@@ -24,12 +42,24 @@ impl Default for HitRecord {
             p: Point3::default(),
             normal: Vec3::default(),
             t: f64::default(),
+            u: f64::default(),
+            v: f64::default(),
             front_face: bool::default(),
             mat: Arc::new(Lambertian::default()),
+            vertex_color: None,
+            object_id: 0,
         }
     }
 }
 
+static NEXT_OBJECT_ID: AtomicU32 = AtomicU32::new(1);
+
+// Hands out the ids written to the object id render layer. Id 0 is reserved for rays
+// that hit nothing, and ids follow scene construction order.
+pub fn next_object_id() -> u32 {
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
 impl HitRecord {
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
         self.front_face = dot(&r.direction(), &outward_normal) < 0.0;
@@ -44,31 +74,70 @@ impl HitRecord {
 
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool;
+    fn bounding_box(&self) -> Aabb;
+
+    // Per-channel weight for a ray that crosses the object over `ray_t` without hitting
+    // it. Volumes whose channels differ in extinction sample collisions with an average
+    // one and correct for it here; everything else lets rays through unchanged.
+    fn transmittance(&self, _r: &Ray, _ray_t: Interval) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    // Whether `transmittance` can be anything but white. It costs a second traversal of
+    // the scene, which is skipped for objects and scenes without such volumes.
+    fn has_transmittance(&self) -> bool {
+        false
+    }
+
+    // Solid angle density with which `random` picks `direction` from `origin`, with the
+    // object where it is at `time`. Only objects used as lights need to implement this
+    // and `random`.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
+        0.0
+    }
+
+    // A direction from `origin` toward a random point on the object at `time`.
+    fn random(&self, _origin: &Point3, _time: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
 
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
+    has_transmittance: bool,
 }
 
 impl HittableList {
     pub fn empty() -> Self {
         Self {
             objects: Vec::new(),
+            bbox: aabb::EMPTY,
+            has_transmittance: false,
         }
     }
 
     pub fn from<T: Hittable + 'static>(objects: Vec<Arc<T>>) -> Self {
-        Self {
-            objects: objects
-                .into_iter()
-                .map(|o| o as Arc<dyn Hittable>)
-                .collect(),
+        let mut ret = Self::empty();
+        for o in objects {
+            ret.add(o);
         }
+        ret
     }
 
     pub fn add(&mut self, obj: Arc<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &obj.bounding_box());
+        self.has_transmittance |= obj.has_transmittance();
         self.objects.push(obj);
     }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
 
 impl Hittable for HittableList {
@@ -86,12 +155,255 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.objects
+            .iter()
+            .filter(|obj| obj.has_transmittance())
+            .fold(Color::new(1.0, 1.0, 1.0), |weight, obj| {
+                weight * obj.transmittance(r, ray_t)
+            })
+    }
+
+    fn has_transmittance(&self) -> bool {
+        self.has_transmittance
+    }
+
+    // Objects are sampled with equal probability, whatever their size.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|obj| obj.pdf_value(origin, direction, time))
+            .sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = random_int(0, self.objects.len() as i32 - 1) as usize;
+        self.objects[index].random(origin, time)
+    }
 }
 
 impl Clone for HittableList {
     fn clone(&self) -> Self {
         Self {
             objects: self.objects.clone(),
+            bbox: self.bbox,
+            has_transmittance: self.has_transmittance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{
+        density::GridDensity,
+        hittable::{
+            bvh::BvhNode, heterogeneous_medium::HeterogeneousMedium, instance::Instance,
+            mesh::TriangleMesh, quad::Quad, sphere::Sphere, triangle::Triangle,
+        },
+        onb::Onb,
+        transform::Transform,
+        utility::random_double,
+    };
+
+    const SAMPLES: usize = 200_000;
+
+    // Uniform directions from `origin` over the cone around the object's bounding box,
+    // or the whole sphere from inside it, along with their density.
+    fn uniform_direction(object: &dyn Hittable, origin: &Point3) -> (Vec3, f64) {
+        let bbox = object.bounding_box();
+        let center = Point3::new(bbox.centroid(0), bbox.centroid(1), bbox.centroid(2));
+        let radius = 0.5 * Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).length();
+        let distance_squared = (center - origin).length_squared();
+        if distance_squared <= radius * radius {
+            return (Vec3::random_unit_vector(), 1.0 / (4.0 * PI));
         }
+
+        let cos_theta_max = (1.0 - radius * radius / distance_squared).sqrt();
+        let z = 1.0 + random_double() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * random_double();
+        let sin_theta = (1.0 - z * z).sqrt();
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), z);
+        let direction = Onb::new(&(center - origin)).transform(&local);
+        (direction, 1.0 / (2.0 * PI * (1.0 - cos_theta_max)))
+    }
+
+    // Checks that pdf_value is a density over the directions `random` produces: it
+    // integrates to one, and E[1 / pdf_value] over sampled directions is the solid
+    // angle the object covers, both estimated with uniformly drawn directions.
+    fn assert_sampling_matches(object: &dyn Hittable, origin: &Point3, time: f64) {
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut integral = 0.0;
+        let mut solid_angle = 0.0;
+        for _ in 0..SAMPLES {
+            let (direction, uniform_pdf) = uniform_direction(object, origin);
+            integral += object.pdf_value(origin, &direction, time) / uniform_pdf;
+            let r = Ray::with_time(*origin, direction, time);
+            if object.hit(&r, ray_t, &mut HitRecord::default()) {
+                solid_angle += 1.0 / uniform_pdf;
+            }
+        }
+        let integral = integral / SAMPLES as f64;
+        let solid_angle = solid_angle / SAMPLES as f64;
+        assert!((integral - 1.0).abs() < 0.05, "integrates to {}", integral);
+
+        let mut inverse_sum = 0.0;
+        for _ in 0..SAMPLES {
+            let direction = object.random(origin, time);
+            let r = Ray::with_time(*origin, direction, time);
+            assert!(
+                object.hit(&r, ray_t, &mut HitRecord::default()),
+                "sampled {:?}, which misses",
+                direction
+            );
+            let pdf = object.pdf_value(origin, &direction, time);
+            assert!(pdf > 0.0, "sampled {:?} with zero density", direction);
+            inverse_sum += 1.0 / pdf;
+        }
+        let estimate = inverse_sum / SAMPLES as f64;
+        let error = (estimate - solid_angle).abs() / solid_angle;
+        assert!(error < 0.05, "estimated {} for {}", estimate, solid_angle);
+    }
+
+    fn mat() -> Arc<dyn Material> {
+        Arc::new(Lambertian::default())
+    }
+
+    fn quad() -> Quad {
+        Quad::new(
+            Point3::new(-1.0, -0.5, 2.0),
+            Vec3::new(2.0, 0.0, 0.5),
+            Vec3::new(0.0, 1.5, 0.0),
+            mat(),
+        )
+    }
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(-1.0, -1.0, 1.5),
+            Point3::new(1.5, -0.5, 2.0),
+            Point3::new(0.0, 1.0, 1.0),
+            mat(),
+        )
+    }
+
+    #[test]
+    fn quad_sampling() {
+        assert_sampling_matches(&quad(), &Point3::new(0.0, 0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn sphere_sampling() {
+        let sphere = Sphere::new(Point3::new(0.5, 0.0, 2.0), 1.0, Lambertian::default());
+        assert_sampling_matches(&sphere, &Point3::new(0.0, 0.0, 0.0), 0.0);
+        assert_sampling_matches(&sphere, &Point3::new(0.5, 0.3, 2.0), 0.0);
+
+        // A moving sphere is sampled where it is at the given time.
+        let moving = Sphere::moving(
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(0.0, 3.0, 2.0),
+            1.0,
+            Lambertian::default(),
+        );
+        assert_sampling_matches(&moving, &Point3::new(0.0, 0.0, 0.0), 0.7);
+    }
+
+    #[test]
+    fn triangle_sampling() {
+        assert_sampling_matches(&triangle(), &Point3::new(0.0, 0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn instance_sampling() {
+        let transform = Transform::scale(Vec3::new(1.5, 0.5, 1.0))
+            .unwrap()
+            .then(&Transform::rotate(&Vec3::new(1.0, 1.0, 0.0), 30.0))
+            .then(&Transform::translate(Vec3::new(0.0, 0.5, 0.5)));
+        let instance = Instance::new(Arc::new(quad()), transform);
+        assert_sampling_matches(&instance, &Point3::new(0.0, 0.0, 0.0), 0.0);
+
+        let moving =
+            Instance::new(Arc::new(triangle()), transform).with_motion(Vec3::new(1.0, 0.0, 0.5));
+        assert_sampling_matches(&moving, &Point3::new(0.0, 0.0, 0.0), 0.6);
+    }
+
+    #[test]
+    fn aggregate_sampling() {
+        let mesh = TriangleMesh::new(
+            vec![
+                Point3::new(-1.0, -1.0, 2.0),
+                Point3::new(1.0, -1.0, 2.0),
+                Point3::new(1.0, 1.0, 2.5),
+                Point3::new(-1.0, 1.0, 2.5),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+            None,
+            None,
+            mat(),
+        );
+        assert_sampling_matches(&mesh, &Point3::new(0.0, 0.0, 0.0), 0.0);
+
+        let mut list = HittableList::empty();
+        list.add(Arc::new(quad()));
+        list.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.8,
+            Lambertian::default(),
+        )));
+        list.add(Arc::new(triangle()));
+        let bvh = BvhNode::new(list.clone());
+        assert_sampling_matches(&list, &Point3::new(0.0, 0.0, 0.0), 0.0);
+        assert_sampling_matches(&bvh, &Point3::new(0.0, 0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn tracks_which_objects_tint_rays() {
+        let unit_sphere = || {
+            Arc::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                1.0,
+                Lambertian::default(),
+            ))
+        };
+        let medium = |sigma_a: Color| {
+            let bbox = Aabb::from_points(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+            let density = GridDensity::new([1, 1, 1], bbox, vec![1.0]).unwrap();
+            Arc::new(HeterogeneousMedium::new(
+                unit_sphere(),
+                Arc::new(density),
+                sigma_a,
+                Color::new(0.5, 0.5, 0.5),
+            ))
+        };
+        let gray = medium(Color::new(0.2, 0.2, 0.2));
+        let tinted = medium(Color::new(0.1, 0.2, 0.3));
+        assert!(!gray.has_transmittance());
+        assert!(tinted.has_transmittance());
+
+        let mut world = HittableList::empty();
+        world.add(unit_sphere());
+        world.add(gray);
+        assert!(!world.has_transmittance());
+        assert!(!BvhNode::new(world.clone()).has_transmittance());
+
+        let instance = Instance::new(tinted, Transform::translate(Vec3::new(3.0, 0.0, 0.0)));
+        world.add(Arc::new(instance));
+        assert!(world.has_transmittance());
+        assert!(BvhNode::new(world).has_transmittance());
     }
 }
@@ -0,0 +1,274 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::{
+    aabb::{self, Aabb},
+    interval::Interval,
+    ray::Ray,
+    utility::random_double,
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{HitRecord, Hittable, HittableList};
+
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+    has_transmittance: bool,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> Self {
+        let mut objects = list.objects;
+        if objects.is_empty() {
+            let empty: Arc<dyn Hittable> = Arc::new(HittableList::empty());
+            return Self {
+                left: empty.clone(),
+                right: empty,
+                bbox: aabb::EMPTY,
+                has_transmittance: false,
+            };
+        }
+        Self::build(&mut objects)
+    }
+
+    fn build(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let bbox = objects.iter().fold(aabb::EMPTY, |bbox, o| {
+            Aabb::surrounding(&bbox, &o.bounding_box())
+        });
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            n => {
+                // Split at the median centroid along the longest axis of the node.
+                let axis = bbox.longest_axis();
+                objects.sort_unstable_by(|a, b| Self::box_compare(a, b, axis));
+
+                let (lo, hi) = objects.split_at_mut(n / 2);
+                (Arc::new(Self::build(lo)), Arc::new(Self::build(hi)))
+            }
+        };
+
+        let has_transmittance = left.has_transmittance() || right.has_transmittance();
+        Self {
+            left,
+            right,
+            bbox,
+            has_transmittance,
+        }
+    }
+
+    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
+        let a_center = a.bounding_box().centroid(axis);
+        let b_center = b.bounding_box().centroid(axis);
+        a_center.total_cmp(&b_center)
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        // A leaf with one object holds it as both children. Testing it twice would give a
+        // volume two chances to scatter the ray.
+        let hit_left = self.left.hit(r, ray_t, record);
+        if Arc::ptr_eq(&self.left, &self.right) {
+            return hit_left;
+        }
+        let right_t = Interval::new(ray_t.min, if hit_left { record.t } else { ray_t.max });
+        let hit_right = self.right.hit(r, right_t, record);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Only subtrees holding volumes that tint rays are visited.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        if !self.has_transmittance || !self.bbox.hit(r, ray_t) {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let left = self.left.transmittance(r, ray_t);
+        if Arc::ptr_eq(&self.left, &self.right) {
+            return left;
+        }
+        left * self.right.transmittance(r, ray_t)
+    }
+
+    fn has_transmittance(&self) -> bool {
+        self.has_transmittance
+    }
+
+    // Each child is picked with equal chance, so the density is the average of the
+    // children's. A leaf with one object holds it as both children, which averages out.
+    // Only subtrees whose box the direction passes through can contribute.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let r = Ray::with_time(*origin, *direction, time);
+        if !self.bbox.hit(&r, Interval::new(0.001, f64::INFINITY)) {
+            return 0.0;
+        }
+        0.5 * (self.left.pdf_value(origin, direction, time)
+            + self.right.pdf_value(origin, direction, time))
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        if random_double() < 0.5 {
+            self.left.random(origin, time)
+        } else {
+            self.right.random(origin, time)
+        }
+    }
+}
+
+impl From<HittableList> for BvhNode {
+    fn from(list: HittableList) -> Self {
+        Self::new(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    use super::*;
+    use crate::{
+        hittable::{quad::Quad, sphere::Sphere},
+        material::Lambertian,
+        utility::{random_double, random_double_range},
+        vec3::{Point3, Vec3},
+    };
+
+    fn random_point(extent: f64) -> Point3 {
+        Point3::new(
+            random_double_range(-extent, extent),
+            random_double_range(-extent, extent),
+            random_double_range(-extent, extent),
+        )
+    }
+
+    fn random_scene(count: usize) -> HittableList {
+        let mat = Arc::new(Lambertian::default());
+        let mut list = HittableList::empty();
+        for _ in 0..count {
+            if random_double() < 0.5 {
+                list.add(Arc::new(Sphere::new(
+                    random_point(10.0),
+                    random_double_range(0.1, 1.5),
+                    Lambertian::default(),
+                )));
+            } else {
+                list.add(Arc::new(Quad::new(
+                    random_point(10.0),
+                    Vec3::random_range(-2.0, 2.0),
+                    Vec3::random_range(-2.0, 2.0),
+                    mat.clone(),
+                )));
+            }
+        }
+        list
+    }
+
+    // Returns how many of the rays hit something.
+    fn assert_same_hits(list: &HittableList, bvh: &BvhNode, rays: usize) -> usize {
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut hits = 0;
+        for _ in 0..rays {
+            // Aim through the scene's volume so most rays pass near some object.
+            let origin = random_point(15.0);
+            let r = Ray::new(origin, random_point(10.0) - origin);
+
+            let mut expected = HitRecord::default();
+            let mut actual = HitRecord::default();
+            let hit_list = list.hit(&r, ray_t, &mut expected);
+            let hit_bvh = bvh.hit(&r, ray_t, &mut actual);
+
+            assert_eq!(hit_list, hit_bvh, "hit/miss differs for {:?}", r);
+            if hit_list {
+                hits += 1;
+                assert!(
+                    (expected.t - actual.t).abs() < 1e-9,
+                    "t differs for {:?}",
+                    r
+                );
+                assert!(
+                    (expected.normal - actual.normal).near_zero(),
+                    "normal differs for {:?}",
+                    r
+                );
+                assert_eq!(expected.object_id, actual.object_id);
+            }
+        }
+        hits
+    }
+
+    #[test]
+    fn matches_linear_list() {
+        let mut hits = 0;
+        for count in [1, 2, 3, 10, 200] {
+            let list = random_scene(count);
+            let bvh = BvhNode::new(list.clone());
+            hits += assert_same_hits(&list, &bvh, 5000);
+        }
+        assert!(hits > 1000, "too few rays hit the scenes: {}", hits);
+    }
+
+    // Counts how often it's tested, for checking each object is tested once per ray.
+    struct Counted {
+        object: Sphere,
+        tests: AtomicUsize,
+    }
+
+    impl Hittable for Counted {
+        fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+            self.tests.fetch_add(1, AtomicOrdering::Relaxed);
+            self.object.hit(r, ray_t, record)
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            self.object.bounding_box()
+        }
+    }
+
+    #[test]
+    fn tests_single_object_leaves_once() {
+        for count in [1, 3] {
+            let objects: Vec<Arc<Counted>> = (0..count)
+                .map(|i| {
+                    Arc::new(Counted {
+                        object: Sphere::new(
+                            Point3::new(3.0 * i as f64, 0.0, 0.0),
+                            1.0,
+                            Lambertian::default(),
+                        ),
+                        tests: AtomicUsize::new(0),
+                    })
+                })
+                .collect();
+            let bvh = BvhNode::new(HittableList::from(objects.clone()));
+
+            for object in &objects {
+                for o in &objects {
+                    o.tests.store(0, AtomicOrdering::Relaxed);
+                }
+                let center = object.object.center;
+                let r = Ray::new(center + Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+                let mut rec = HitRecord::default();
+                assert!(bvh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+                assert_eq!(object.tests.load(AtomicOrdering::Relaxed), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_bvh_hits_nothing() {
+        let bvh = BvhNode::new(HittableList::empty());
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::default();
+        assert!(!bvh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    interval::{self, Interval},
+    material::{Isotropic, Material},
+    ray::Ray,
+    texture::Texture,
+    utility::random_double,
+    vec3::{Color, Vec3},
+};
+
+use super::{next_object_id, HitRecord, Hittable};
+
+// A volume of uniform density filling a boundary object, such as smoke or fog. Rays
+// passing through scatter after an exponentially distributed distance. The boundary
+// must be convex, since only the first entry and exit are considered.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+    id: u32,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::from_texture(tex)),
+            id: next_object_id(),
+        }
+    }
+
+    pub fn from_color(boundary: Arc<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::new(albedo)),
+            id: next_object_id(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let Some(span) = span_inside(self.boundary.as_ref(), r, ray_t) else {
+            return false;
+        };
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = span.size() * ray_length;
+        let hit_distance = self.neg_inv_density * random_double().ln();
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        let t = span.min + hit_distance / ray_length;
+        fill_record(record, r, t, self.phase_function.clone(), self.id);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+// The part of `ray_t` where the ray is inside a convex boundary, even if it starts
+// inside.
+pub(super) fn span_inside(boundary: &dyn Hittable, r: &Ray, ray_t: Interval) -> Option<Interval> {
+    let mut rec1 = HitRecord::default();
+    if !boundary.hit(r, interval::UNIVERSE, &mut rec1) {
+        return None;
+    }
+    let mut rec2 = HitRecord::default();
+    if !boundary.hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2) {
+        return None;
+    }
+
+    let t_enter = rec1.t.max(ray_t.min).max(0.0);
+    let t_exit = rec2.t.min(ray_t.max);
+    (t_enter < t_exit).then(|| Interval::new(t_enter, t_exit))
+}
+
+// Records a collision at `t` inside a volume.
+pub(super) fn fill_record(
+    record: &mut HitRecord,
+    r: &Ray,
+    t: f64,
+    mat: Arc<dyn Material>,
+    object_id: u32,
+) {
+    record.t = t;
+    record.p = r.at(t);
+    // A point in a volume has no surface, so the normal is arbitrary.
+    record.normal = Vec3::new(1.0, 0.0, 0.0);
+    record.front_face = true;
+    (record.u, record.v) = (0.0, 0.0);
+    record.mat = mat;
+    record.vertex_color = None;
+    record.object_id = object_id;
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    density::Density,
+    interval::Interval,
+    material::{HenyeyGreenstein, Material},
+    ray::Ray,
+    utility::random_double,
+    vec3::Color,
+};
+
+use super::{
+    constant_medium::{fill_record, span_inside},
+    next_object_id, HitRecord, Hittable,
+};
+
+// A volume whose density varies through space, filling a convex boundary object. The
+// absorption and scattering coefficients are per color channel and scaled by the
+// density at each point.
+//
+// Collisions are found by delta tracking with the extinction averaged over the
+// channels. Channels that absorb or scatter more or less than that average are
+// corrected for by the phase function's albedo at a collision, and by `transmittance`
+// along the way to whatever the ray hits, which the camera applies.
+pub struct HeterogeneousMedium {
+    boundary: Arc<dyn Hittable>,
+    density: Arc<dyn Density>,
+    sigma_a: Color,
+    sigma_s: Color,
+    phase_function: Arc<dyn Material>,
+    // Ends paths that are absorbed inside the volume.
+    absorber: Arc<dyn Material>,
+    id: u32,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: Arc<dyn Density>,
+        sigma_a: Color,
+        sigma_s: Color,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            sigma_a,
+            sigma_s,
+            phase_function: Arc::new(HenyeyGreenstein::new(0.0, scattering_albedo(&sigma_s))),
+            absorber: Arc::new(Absorber),
+            id: next_object_id(),
+        }
+    }
+
+    // Sets the Henyey–Greenstein asymmetry of the phase function; isotropic by default.
+    pub fn with_anisotropy(mut self, g: f64) -> Self {
+        self.phase_function = Arc::new(HenyeyGreenstein::new(g, scattering_albedo(&self.sigma_s)));
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // How much each channel's extinction differs from the average over the channels.
+    fn extinction_excess(&self) -> Color {
+        let sigma_t = self.sigma_a + self.sigma_s;
+        let average_t = average(&sigma_t);
+        sigma_t - Color::new(average_t, average_t, average_t)
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let Some(span) = span_inside(self.boundary.as_ref(), r, ray_t) else {
+            return false;
+        };
+
+        let sigma_t = average(&(self.sigma_a + self.sigma_s));
+        let majorant = self.density.max_density() * sigma_t;
+        if majorant <= 0.0 {
+            return false;
+        }
+
+        let ray_length = r.direction().length();
+        let mut t = span.min;
+        loop {
+            t -= (1.0 - random_double()).ln() / (majorant * ray_length);
+            if t >= span.max {
+                return false;
+            }
+
+            // A real collision in proportion to the extinction here, else a null one.
+            let density = self.density.density(&r.at(t));
+            if random_double() * majorant < density * sigma_t {
+                let mat = if random_double() * sigma_t < average(&self.sigma_s) {
+                    self.phase_function.clone()
+                } else {
+                    self.absorber.clone()
+                };
+                fill_record(record, r, t, mat, self.id);
+                return true;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    // The ratio of each channel's transmittance to the average channel's, which `hit`
+    // used, estimated by ratio tracking over the difference in extinction.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        let excess = self.extinction_excess();
+        if excess.near_zero() {
+            return weight;
+        }
+        let Some(span) = span_inside(self.boundary.as_ref(), r, ray_t) else {
+            return weight;
+        };
+
+        let largest = excess.x().abs().max(excess.y().abs()).max(excess.z().abs());
+        let majorant = self.density.max_density() * largest;
+        if majorant <= 0.0 {
+            return weight;
+        }
+
+        let ray_length = r.direction().length();
+        let mut t = span.min;
+        loop {
+            t -= (1.0 - random_double()).ln() / (majorant * ray_length);
+            if t >= span.max {
+                return weight;
+            }
+            let density = self.density.density(&r.at(t));
+            weight *= Color::new(1.0, 1.0, 1.0) - excess * (density / majorant);
+        }
+    }
+
+    // Only volumes whose channels differ in extinction need correcting.
+    fn has_transmittance(&self) -> bool {
+        !self.extinction_excess().near_zero()
+    }
+}
+
+// Ends the path without scattering or emitting.
+struct Absorber;
+
+impl Material for Absorber {}
+
+// Scattering picks a channel-averaged event, so each channel is weighted by how its
+// scattering compares with the average.
+fn scattering_albedo(sigma_s: &Color) -> Color {
+    let average_s = average(sigma_s);
+    if average_s <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    *sigma_s / average_s
+}
+
+fn average(c: &Color) -> f64 {
+    (c.x() + c.y() + c.z()) / 3.0
+}
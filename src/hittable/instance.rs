@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    ray::Ray,
+    transform::Transform,
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{HitRecord, Hittable};
+
+// Places a shared object in the scene with an affine transform. Rays are moved into the
+// object's space for the hit test and the hit point and normal are moved back out, so
+// one mesh can be instanced many times without copying it.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    // Extra translation reached at time 1, for motion blur.
+    motion: Vec3,
+    bbox: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bbox = transform.apply_box(&object.bounding_box());
+        Self {
+            object,
+            transform,
+            motion: Vec3::new(0.0, 0.0, 0.0),
+            bbox,
+        }
+    }
+
+    // Moves the transformed object in a straight line by `displacement` between time 0
+    // and time 1.
+    pub fn with_motion(mut self, displacement: Vec3) -> Self {
+        let end = Transform::translate(displacement).apply_box(&self.bbox);
+        self.motion = displacement;
+        self.bbox = Aabb::surrounding(&self.bbox, &end);
+        self
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    // Moves a world ray into the object's space at the ray's time.
+    fn object_ray(&self, r: &Ray) -> Ray {
+        let offset = self.motion * r.time();
+        let moved_ray = Ray::with_time(r.origin() - offset, r.direction(), r.time());
+        self.transform.inverse().apply_ray(&moved_ray)
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        if !self.object.hit(&self.object_ray(r), ray_t, record) {
+            return false;
+        }
+
+        // The object ray's direction isn't normalized, so t carries over unchanged.
+        let offset = self.motion * r.time();
+        record.p = self.transform.apply_point(&record.p) + offset;
+        record.normal = self.transform.apply_normal(&record.normal);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        self.object.transmittance(&self.object_ray(r), ray_t)
+    }
+
+    fn has_transmittance(&self) -> bool {
+        self.object.has_transmittance()
+    }
+
+    // Light sampling happens in object space, from the origin moved back by the motion
+    // at `time`. Scaling and shearing stretch the directions, so the density is corrected
+    // by the change in solid angle.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let inverse = self.transform.inverse();
+        let object_origin = inverse.apply_point(&(*origin - self.motion * time));
+        let unit_direction = Vec3::unit_vector(direction);
+        let object_direction = inverse.apply_vector(&unit_direction);
+        let object_pdf = self
+            .object
+            .pdf_value(&object_origin, &object_direction, time);
+        if object_pdf == 0.0 {
+            return 0.0;
+        }
+
+        object_pdf * inverse.determinant().abs() / object_direction.length().powi(3)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let object_origin = self
+            .transform
+            .inverse()
+            .apply_point(&(*origin - self.motion * time));
+        self.transform
+            .apply_vector(&self.object.random(&object_origin, time))
+    }
+}
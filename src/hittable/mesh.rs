@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{bvh::BvhNode, next_object_id, triangle, HitRecord, Hittable, HittableList};
+
+struct MeshData {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<Color>>,
+    indices: Vec<[usize; 3]>,
+    mat: Arc<dyn Material>,
+    id: u32,
+}
+
+// One triangle of a mesh, referring to the shared vertex buffers by index.
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [&Point3; 3] {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let p = &self.mesh.positions;
+        [&p[i0], &p[i1], &p[i2]]
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let [a, b, c] = self.vertices();
+        let Some((t, b1, b2)) = triangle::intersect(r, ray_t, a, b, c) else {
+            return false;
+        };
+
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.indices[self.index];
+        let normals = mesh.normals.as_ref().map(|n| [n[i0], n[i1], n[i2]]);
+
+        record.t = t;
+        record.p = r.at(t);
+        (record.u, record.v) = match &mesh.uvs {
+            Some(uvs) => {
+                let b0 = 1.0 - b1 - b2;
+                (
+                    b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                    b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+                )
+            }
+            None => (b1, b2),
+        };
+        triangle::set_normal(record, r, a, b, c, normals.as_ref(), b1, b2);
+        record.mat = mesh.mat.clone();
+        record.vertex_color = mesh
+            .colors
+            .as_ref()
+            .map(|colors| colors[i0] * (1.0 - b1 - b2) + colors[i1] * b1 + colors[i2] * b2);
+        record.object_id = mesh.id;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices();
+        triangle::bounding_box(a, b, c)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, _time: f64) -> f64 {
+        let [a, b, c] = self.vertices();
+        triangle::pdf_value(origin, direction, a, b, c)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let [a, b, c] = self.vertices();
+        triangle::random(origin, a, b, c)
+    }
+}
+
+// Indexed triangles sharing one set of vertex buffers and a single material, with their
+// own BVH. Per-vertex normals give smooth shading, per-vertex UVs replace the default
+// barycentric coordinates and per-vertex colors tint the material. The whole mesh has a
+// single object id.
+pub struct TriangleMesh {
+    bvh: BvhNode,
+    triangle_count: usize,
+    id: u32,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        Self::build(positions, indices, normals, uvs, None, mat)
+    }
+
+    // Like `new`, with a color for each vertex that's interpolated across the triangles
+    // and multiplied into the material's color.
+    pub fn with_vertex_colors(
+        positions: Vec<Point3>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        colors: Vec<Color>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        Self::build(positions, indices, normals, uvs, Some(colors), mat)
+    }
+
+    fn build(
+        positions: Vec<Point3>,
+        indices: Vec<[usize; 3]>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        colors: Option<Vec<Color>>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let vertex_count = positions.len();
+        assert!(
+            indices.iter().flatten().all(|&i| i < vertex_count),
+            "triangle index out of range"
+        );
+        assert!(
+            normals.as_ref().is_none_or(|n| n.len() == vertex_count),
+            "normal count does not match vertex count"
+        );
+        assert!(
+            uvs.as_ref().is_none_or(|uv| uv.len() == vertex_count),
+            "uv count does not match vertex count"
+        );
+        assert!(
+            colors.as_ref().is_none_or(|c| c.len() == vertex_count),
+            "color count does not match vertex count"
+        );
+
+        let id = next_object_id();
+        let triangle_count = indices.len();
+        let mesh = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            colors,
+            indices,
+            mat,
+            id,
+        });
+
+        let mut triangles = HittableList::empty();
+        for index in 0..triangle_count {
+            triangles.add(Arc::new(MeshTriangle {
+                mesh: mesh.clone(),
+                index,
+            }));
+        }
+
+        Self {
+            bvh: BvhNode::new(triangles),
+            triangle_count,
+            id,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        self.bvh.hit(r, ray_t, record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.bvh.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        self.bvh.random(origin, time)
+    }
+}
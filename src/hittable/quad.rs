@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    utility::random_double,
+    vec3::{cross, dot, Point3, Vec3},
+};
+
+use super::{next_object_id, HitRecord, Hittable, HittableList};
+
+// A parallelogram with corner `q` and edges `u` and `v`. The front face is the one
+// `cross(u, v)` points out of.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    // Maps a point on the plane to its (alpha, beta) coordinates along u and v.
+    w: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    id: u32,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        let normal = Vec3::unit_vector(&n);
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+
+        Self {
+            q,
+            u,
+            v,
+            w: n / dot(&n, &n),
+            mat,
+            bbox: Aabb::surrounding(&bbox_diagonal1, &bbox_diagonal2),
+            normal,
+            d: dot(&normal, &q),
+            area: n.length(),
+            id: next_object_id(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // The quad covers the unit square of its own plane coordinates.
+    fn is_interior(a: f64, b: f64) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+        unit_interval.contains(a) && unit_interval.contains(b)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let denom = dot(&self.normal, &r.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - dot(&self.normal, &r.origin())) / denom;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(&self.w, &cross(&planar_hitpt_vector, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar_hitpt_vector));
+
+        if !Quad::is_interior(alpha, beta) {
+            return false;
+        }
+
+        record.t = t;
+        record.p = intersection;
+        record.u = alpha;
+        record.v = beta;
+        record.set_face_normal(r, self.normal);
+        record.mat = self.mat.clone();
+        record.vertex_color = None;
+        record.object_id = self.id;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Points are picked uniformly over the area, so the solid angle density is the area
+    // density scaled by how far away and how tilted the hit point is.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, _time: f64) -> f64 {
+        let mut rec = HitRecord::default();
+        let r = Ray::new(*origin, *direction);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (dot(direction, &rec.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let p = self.q + (self.u * random_double()) + (self.v * random_double());
+        p - origin
+    }
+}
+
+// Builds the six sides of the axis-aligned box with opposite corners `a` and `b`,
+// all facing outward.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::empty();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy), // front
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy), // right
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy), // back
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy), // left
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz), // top
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz), // bottom
+    ];
+    for (q, u, v) in faces {
+        sides.add(Arc::new(Quad::new(q, u, v, mat.clone())));
+    }
+
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    // Like the other primitives, hits exactly at either end of the interval don't count.
+    #[test]
+    fn ignores_hits_at_interval_ends() {
+        let quad = Quad::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Arc::new(Lambertian::default()),
+        );
+        let r = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let mut rec = HitRecord::default();
+        assert!(quad.hit(&r, Interval::new(0.5, 2.0), &mut rec));
+        assert_eq!(rec.t, 1.0);
+        assert!(!quad.hit(&r, Interval::new(1.0, 2.0), &mut rec));
+        assert!(!quad.hit(&r, Interval::new(0.5, 1.0), &mut rec));
+    }
+}
@@ -1,17 +1,24 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
+    aabb::Aabb,
     interval::Interval,
-    material::{Lambertian, Material},
-    vec3::{dot, Point3},
+    material::Material,
+    onb::Onb,
+    ray::Ray,
+    utility::random_double,
+    vec3::{dot, Point3, Vec3},
 };
 
-use super::{HitRecord, Hittable};
+use super::{next_object_id, HitRecord, Hittable};
 
 pub struct Sphere {
+    // Center at time 0; a moving sphere travels by `motion` by time 1.
     pub center: Point3,
     pub radius: f64,
     pub mat: Arc<dyn Material>,
+    motion: Vec3,
+    id: u32,
 }
 
 impl Sphere {
@@ -20,13 +27,46 @@ impl Sphere {
             center: c,
             radius: r.max(0.0),
             mat: Arc::new(m),
+            motion: Vec3::new(0.0, 0.0, 0.0),
+            id: next_object_id(),
         }
     }
+
+    // A sphere moving in a straight line from `c1` at time 0 to `c2` at time 1.
+    pub fn moving<M: Material + 'static + Send + Sync>(
+        c1: Point3,
+        c2: Point3,
+        r: f64,
+        m: M,
+    ) -> Self {
+        Self {
+            motion: c2 - c1,
+            ..Self::new(c1, r, m)
+        }
+    }
+
+    pub fn center_at(&self, time: f64) -> Point3 {
+        self.center + self.motion * time
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // Maps a point on the unit sphere to (u, v) in [0, 1]: u is the angle around the Y
+    // axis starting from X = -1, v the angle from Y = -1 up to Y = +1.
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &crate::ray::Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
-        let oc = self.center - r.origin();
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let center = self.center_at(r.time());
+        let oc = center - r.origin();
         let a = r.direction().length_squared();
         let h = dot(&r.direction(), &oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -49,11 +89,54 @@ impl Hittable for Sphere {
 
         record.t = root;
         record.p = r.at(root);
-        let outward_normal = (record.p - self.center) / self.radius;
+        let outward_normal = (record.p - center) / self.radius;
 
         record.set_face_normal(r, outward_normal);
+        (record.u, record.v) = Sphere::get_sphere_uv(&outward_normal);
         record.mat = self.mat.clone();
+        record.vertex_color = None;
+        record.object_id = self.id;
 
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        let start = Aabb::from_points(self.center - rvec, self.center + rvec);
+        let end_center = self.center_at(1.0);
+        let end = Aabb::from_points(end_center - rvec, end_center + rvec);
+        Aabb::surrounding(&start, &end)
+    }
+
+    // Samples the cone of directions the sphere covers as seen from `origin`. From
+    // inside, every direction hits it.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::default();
+        let r = Ray::with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center_at(time) - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3 {
+        let direction = self.center_at(time) - origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return Vec3::random_unit_vector();
+        }
+
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let z = 1.0 + random_double() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * random_double();
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        Onb::new(&direction).transform(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), z))
+    }
 }
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    utility::random_double,
+    vec3::{cross, dot, Point3, Vec3},
+};
+
+use super::{next_object_id, HitRecord, Hittable};
+
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    mat: Arc<dyn Material>,
+    id: u32,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            normals: None,
+            mat,
+            id: next_object_id(),
+        }
+    }
+
+    // Shades with normals interpolated from the vertices instead of the flat face normal.
+    pub fn with_normals(mut self, na: Vec3, nb: Vec3, nc: Vec3) -> Self {
+        self.normals = Some([na, nb, nc]);
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, record: &mut HitRecord) -> bool {
+        let [a, b, c] = &self.vertices;
+        let Some((t, b1, b2)) = intersect(r, ray_t, a, b, c) else {
+            return false;
+        };
+
+        record.t = t;
+        record.p = r.at(t);
+        record.u = b1;
+        record.v = b2;
+        set_normal(record, r, a, b, c, self.normals.as_ref(), b1, b2);
+        record.mat = self.mat.clone();
+        record.vertex_color = None;
+        record.object_id = self.id;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = &self.vertices;
+        bounding_box(a, b, c)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, _time: f64) -> f64 {
+        let [a, b, c] = &self.vertices;
+        pdf_value(origin, direction, a, b, c)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3 {
+        let [a, b, c] = &self.vertices;
+        random(origin, a, b, c)
+    }
+}
+
+// Solid angle density of `random` picking `direction` from `origin`: the area density
+// scaled by how far away and how tilted the hit point is.
+pub(super) fn pdf_value(
+    origin: &Point3,
+    direction: &Vec3,
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+) -> f64 {
+    let r = Ray::new(*origin, *direction);
+    let Some((t, _, _)) = intersect(&r, Interval::new(0.001, f64::INFINITY), a, b, c) else {
+        return 0.0;
+    };
+
+    let n = cross(&(*b - a), &(*c - a));
+    let area = 0.5 * n.length();
+    let distance_squared = t * t * direction.length_squared();
+    let cosine = (dot(direction, &n) / (direction.length() * n.length())).abs();
+    distance_squared / (cosine * area)
+}
+
+// A direction from `origin` toward a point picked uniformly over the triangle's area.
+pub(super) fn random(origin: &Point3, a: &Point3, b: &Point3, c: &Point3) -> Vec3 {
+    let s = random_double().sqrt();
+    let t = random_double();
+    let p = *a * (1.0 - s) + *b * (s * (1.0 - t)) + *c * (s * t);
+    p - origin
+}
+
+// Möller–Trumbore ray/triangle intersection. Returns the ray parameter and the
+// barycentric weights of `b` and `c` at the hit point.
+pub(super) fn intersect(
+    r: &Ray,
+    ray_t: Interval,
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = *b - a;
+    let edge2 = *c - a;
+    let pvec = cross(&r.direction(), &edge2);
+    let det = dot(&edge1, &pvec);
+
+    // The ray is parallel to the triangle's plane.
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = r.origin() - a;
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = cross(&tvec, &edge1);
+    let b2 = dot(&r.direction(), &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &qvec) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+// Orients the record by the geometric normal, then swaps in the interpolated vertex
+// normal on the same side when the triangle has one.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn set_normal(
+    record: &mut HitRecord,
+    r: &Ray,
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+    normals: Option<&[Vec3; 3]>,
+    b1: f64,
+    b2: f64,
+) {
+    let geometric = Vec3::unit_vector(&cross(&(*b - a), &(*c - a)));
+    record.set_face_normal(r, geometric);
+
+    if let Some([na, nb, nc]) = normals {
+        let shading = Vec3::unit_vector(&(*na * (1.0 - b1 - b2) + *nb * b1 + *nc * b2));
+        record.normal = if record.front_face { shading } else { -shading };
+    }
+}
+
+pub(super) fn bounding_box(a: &Point3, b: &Point3, c: &Point3) -> Aabb {
+    Aabb::surrounding(&Aabb::from_points(*a, *b), &Aabb::from_points(*a, *c))
+}
@@ -0,0 +1,225 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    slice::Chunks,
+};
+
+use crate::vec3::Color;
+
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+
+// A framebuffer stored row by row starting at the top-left pixel. Beauty renders hold
+// linear colors; single-channel passes such as depth use other sample types.
+#[derive(Debug, Clone)]
+pub struct Image<T = Color> {
+    width: usize,
+    height: usize,
+    pixels: Vec<T>,
+}
+
+impl<T: Copy + Default> Image<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![T::default(); width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<T>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "pixel count does not match image dimensions"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> T {
+        self.pixels[y * self.width + x]
+    }
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: T) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn pixels(&self) -> &[T] {
+        &self.pixels
+    }
+    pub fn pixels_mut(&mut self) -> &mut [T] {
+        &mut self.pixels
+    }
+
+    pub fn rows(&self) -> Chunks<'_, T> {
+        self.pixels.chunks(self.width.max(1))
+    }
+}
+
+impl Image {
+    // Bilinearly filtered lookup with (0, 0) at the top-left corner of the image and
+    // (1, 1) at the bottom-right. U wraps around and v is clamped to the edges.
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        if self.pixels.is_empty() {
+            return Color::default();
+        }
+
+        let x = u.rem_euclid(1.0) * self.width as f64 - 0.5;
+        let y = v.clamp(0.0, 1.0) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |i: f64| (i as i64).rem_euclid(self.width as i64) as usize;
+        let row = |j: f64| (j.max(0.0) as usize).min(self.height - 1);
+        let (c0, c1) = (column(x0), column(x0 + 1.0));
+        let (r0, r1) = (row(y0), row(y0 + 1.0));
+
+        let top = self.pixel(c0, r0) * (1.0 - fx) + self.pixel(c1, r0) * fx;
+        let bottom = self.pixel(c0, r1) * (1.0 - fx) + self.pixel(c1, r1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    // Reads a linear image, choosing the decoder from the file extension of `path`. 8-bit
+    // formats are converted back to linear with the inverse of the output gamma.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = lowercase_extension(path);
+
+        let mut reader = BufReader::new(File::open(path)?);
+        match extension.as_deref() {
+            Some("hdr") => hdr::decode(&mut reader),
+            Some("pfm") => pfm::decode(&mut reader),
+            Some("png") => png::decode(reader),
+            Some("ppm") => ppm::decode(&mut reader),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no image decoder for {}", path.display()),
+            )),
+        }
+    }
+
+    // Writes the image with the encoder matching the file extension of `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        encoder_for_path(path)?.save(self, path)
+    }
+}
+
+// The beauty image plus whichever first-hit passes were requested. Depth is the distance
+// from the camera to the first hit, or infinity where the camera ray escaped; object id 0
+// marks pixels where nothing was hit.
+#[derive(Debug, Clone)]
+pub struct RenderLayers {
+    pub beauty: Image,
+    pub depth: Option<Image<f64>>,
+    pub normal: Option<Image>,
+    pub albedo: Option<Image>,
+    pub object_id: Option<Image<u32>>,
+}
+
+impl RenderLayers {
+    // Writes every layer to one EXR file. Other formats can't hold the extra passes, so
+    // any other extension is an error.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if lowercase_extension(path).as_deref() != Some("exr") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "render layers can only be saved as EXR, not {}",
+                    path.display()
+                ),
+            ));
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        exr::ExrEncoder.encode_layers(self, &mut writer)?;
+        writer.flush()
+    }
+}
+
+pub trait Encoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()>;
+
+    fn save(&self, image: &Image, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.encode(image, &mut writer)?;
+        writer.flush()
+    }
+}
+
+pub fn encoder_for_path(path: &Path) -> io::Result<Box<dyn Encoder>> {
+    let extension = lowercase_extension(path);
+
+    match extension.as_deref() {
+        Some("ppm") => Ok(Box::new(ppm::PpmEncoder::default())),
+        Some("png") => Ok(Box::new(png::PngEncoder::default())),
+        Some("pfm") => Ok(Box::new(pfm::PfmEncoder)),
+        Some("hdr") => Ok(Box::new(hdr::HdrEncoder)),
+        Some("exr") => Ok(Box::new(exr::ExrEncoder)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no image encoder for {}", path.display()),
+        )),
+    }
+}
+
+// The file extension of `path` in lower case, which is how formats are chosen.
+pub fn lowercase_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+// The most pixels a decoded image may have, which is enough for a 16K panorama.
+const MAX_PIXELS: usize = 1 << 27;
+
+// The number of samples in an image of the size a file's header gives, rejecting sizes
+// that would overflow or are too large to allocate.
+fn sample_count(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|&pixels| pixels <= MAX_PIXELS)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| invalid_data(format!("{}x{} image is too large", width, height)))
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// Reads one whitespace-delimited header token and the single whitespace byte after it,
+// as used by the Netpbm family of formats. Comments from `#` to the end of the line are
+// skipped between tokens.
+fn read_token(r: &mut dyn BufRead) -> io::Result<String> {
+    let mut token = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        r.read_exact(&mut byte)?;
+        if byte[0].is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            break;
+        }
+        if byte[0] == b'#' && token.is_empty() {
+            r.read_until(b'\n', &mut Vec::new())?;
+            continue;
+        }
+        token.push(byte[0]);
+    }
+    String::from_utf8(token).map_err(invalid_data)
+}
@@ -0,0 +1,208 @@
+use std::io::{self, Cursor, Write};
+
+use ::exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Layer, LayerAttributes, SmallVec, WritableImage,
+};
+
+use crate::vec3::Color;
+
+use super::{Encoder, Image, RenderLayers};
+
+// OpenEXR with float32 channels. A plain image is written as R, G, B; render layers add
+// Z for depth, prefixed albedo.* and normal.* channels and a uint id channel in the
+// same part.
+pub struct ExrEncoder;
+
+impl ExrEncoder {
+    pub fn encode_layers(&self, layers: &RenderLayers, w: &mut dyn Write) -> io::Result<()> {
+        let mut channels = color_channels(&layers.beauty, ["R", "G", "B"]);
+        if let Some(depth) = &layers.depth {
+            let samples = depth.pixels().iter().map(|&d| d as f32);
+            channels.push(AnyChannel::new("Z", FlatSamples::F32(samples.collect())));
+        }
+        if let Some(albedo) = &layers.albedo {
+            channels.extend(color_channels(albedo, ["albedo.R", "albedo.G", "albedo.B"]));
+        }
+        if let Some(normal) = &layers.normal {
+            channels.extend(color_channels(normal, ["normal.X", "normal.Y", "normal.Z"]));
+        }
+        if let Some(object_id) = &layers.object_id {
+            let samples = object_id.pixels().to_vec();
+            channels.push(AnyChannel::new("id", FlatSamples::U32(samples)));
+        }
+
+        write_channels(&layers.beauty, channels, w)
+    }
+}
+
+impl Encoder for ExrEncoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        write_channels(image, color_channels(image, ["R", "G", "B"]), w)
+    }
+}
+
+fn color_channels(image: &Image, names: [&str; 3]) -> Vec<AnyChannel<FlatSamples>> {
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let samples = image.pixels().iter().map(|c: &Color| c.e[i] as f32);
+            AnyChannel::new(name, FlatSamples::F32(samples.collect()))
+        })
+        .collect()
+}
+
+fn write_channels(
+    image: &Image,
+    channels: Vec<AnyChannel<FlatSamples>>,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    let layer = Layer::new(
+        (image.width(), image.height()),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+
+    // The exr writer needs to seek back to patch its offset table, so encode in memory.
+    let mut buffer = Cursor::new(Vec::new());
+    ::exr::prelude::Image::from_layer(layer)
+        .write()
+        .to_buffered(&mut buffer)
+        .map_err(io::Error::other)?;
+
+    w.write_all(buffer.get_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use ::exr::prelude::{read, ReadChannels, ReadLayers};
+
+    use super::*;
+
+    fn read_channels(bytes: Vec<u8>) -> Vec<(String, FlatSamples)> {
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(Cursor::new(bytes))
+            .expect("failed to read back the EXR file");
+        assert_eq!(image.layer_data.size.0, 3);
+        assert_eq!(image.layer_data.size.1, 2);
+
+        image
+            .layer_data
+            .channel_data
+            .list
+            .into_iter()
+            .map(|channel| (channel.name.to_string(), channel.sample_data))
+            .collect()
+    }
+
+    fn f32_samples(channels: &[(String, FlatSamples)], name: &str) -> Vec<f32> {
+        match channels.iter().find(|(n, _)| n == name) {
+            Some((_, FlatSamples::F32(samples))) => samples.clone(),
+            _ => panic!("no float channel {}", name),
+        }
+    }
+
+    fn color_image(offset: f64) -> Image {
+        let pixels = (0..6)
+            .map(|i| Color::new(i as f64 + offset, 0.5 * i as f64, -(i as f64)))
+            .collect();
+        Image::from_pixels(3, 2, pixels)
+    }
+
+    fn assert_color_channels(channels: &[(String, FlatSamples)], image: &Image, names: [&str; 3]) {
+        for (axis, name) in names.into_iter().enumerate() {
+            let expected: Vec<f32> = image.pixels().iter().map(|c| c.e[axis] as f32).collect();
+            assert_eq!(f32_samples(channels, name), expected, "channel {}", name);
+        }
+    }
+
+    #[test]
+    fn round_trips_plain_image() {
+        let image = color_image(0.25);
+        let mut bytes = Vec::new();
+        ExrEncoder.encode(&image, &mut bytes).unwrap();
+
+        let channels = read_channels(bytes);
+        let names: Vec<&str> = channels.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["B", "G", "R"]);
+        assert_color_channels(&channels, &image, ["R", "G", "B"]);
+    }
+
+    #[test]
+    fn round_trips_render_layers() {
+        let layers = RenderLayers {
+            beauty: color_image(0.25),
+            depth: Some(Image::from_pixels(
+                3,
+                2,
+                vec![1.0, 2.5, f64::INFINITY, 4.0, 0.125, 6.0],
+            )),
+            normal: Some(color_image(-1.0)),
+            albedo: Some(color_image(0.75)),
+            object_id: Some(Image::from_pixels(3, 2, vec![0, 7, 7, 3, 0, 42])),
+        };
+        let mut bytes = Vec::new();
+        ExrEncoder.encode_layers(&layers, &mut bytes).unwrap();
+
+        let channels = read_channels(bytes);
+        let mut names: Vec<&str> = channels.iter().map(|(n, _)| n.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                "B", "G", "R", "Z", "albedo.B", "albedo.G", "albedo.R", "id", "normal.X",
+                "normal.Y", "normal.Z"
+            ]
+        );
+
+        assert_color_channels(&channels, &layers.beauty, ["R", "G", "B"]);
+        assert_color_channels(
+            &channels,
+            layers.albedo.as_ref().unwrap(),
+            ["albedo.R", "albedo.G", "albedo.B"],
+        );
+        assert_color_channels(
+            &channels,
+            layers.normal.as_ref().unwrap(),
+            ["normal.X", "normal.Y", "normal.Z"],
+        );
+        assert_eq!(
+            f32_samples(&channels, "Z"),
+            [1.0, 2.5, f32::INFINITY, 4.0, 0.125, 6.0]
+        );
+        match channels.iter().find(|(n, _)| n == "id") {
+            Some((_, FlatSamples::U32(ids))) => assert_eq!(ids, &[0, 7, 7, 3, 0, 42]),
+            _ => panic!("no uint id channel"),
+        }
+    }
+
+    #[test]
+    fn saves_render_layers_only_as_exr() {
+        let layers = RenderLayers {
+            beauty: color_image(0.5),
+            depth: None,
+            normal: None,
+            albedo: None,
+            object_id: None,
+        };
+        let dir = std::env::temp_dir();
+        for name in ["layers.png", "layers.ppm", "layers"] {
+            let path = dir.join(format!("{}_{}", std::process::id(), name));
+            let err = layers.save(&path).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(!path.exists(), "{} was written", path.display());
+        }
+
+        let path = dir.join(format!("{}_layers.EXR", std::process::id()));
+        layers.save(&path).unwrap();
+        let channels = read_channels(std::fs::read(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(channels.len(), 3);
+    }
+}
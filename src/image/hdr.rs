@@ -0,0 +1,298 @@
+use std::io::{self, BufRead, Write};
+
+use crate::vec3::Color;
+
+use super::{invalid_data, sample_count, Encoder, Image};
+
+// Radiance RGBE (.hdr) with run-length encoded scanlines, storing unclamped linear color.
+pub struct HdrEncoder;
+
+// Scanlines outside this width range cannot use the run-length encoding.
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+
+impl HdrEncoder {
+    fn write_rle_channel(data: &[u8], out: &mut Vec<u8>) {
+        let n = data.len();
+        let mut i = 0;
+        while i < n {
+            let run = Self::run_length(data, i, 127);
+            if run >= 4 {
+                out.push(128 + run as u8);
+                out.push(data[i]);
+                i += run;
+                continue;
+            }
+
+            // Gather literals until the next worthwhile run or the 128 byte limit.
+            let start = i;
+            while i < n && i - start < 128 && Self::run_length(data, i, 4) < 4 {
+                i += 1;
+            }
+            out.push((i - start) as u8);
+            out.extend_from_slice(&data[start..i]);
+        }
+    }
+
+    fn run_length(data: &[u8], start: usize, max: usize) -> usize {
+        data[start..]
+            .iter()
+            .take(max)
+            .take_while(|&&b| b == data[start])
+            .count()
+    }
+}
+
+impl Encoder for HdrEncoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        write!(
+            w,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            image.height(),
+            image.width()
+        )?;
+
+        let width = image.width();
+        let use_rle = (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width);
+        let mut channels = vec![0u8; width * 4];
+        let mut out = Vec::with_capacity(width * 4 + 4);
+
+        for row in image.rows() {
+            out.clear();
+            if use_rle {
+                for (x, color) in row.iter().enumerate() {
+                    let rgbe = color_to_rgbe(color);
+                    for (c, byte) in rgbe.into_iter().enumerate() {
+                        channels[c * width + x] = byte;
+                    }
+                }
+                out.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+                for channel in channels.chunks(width) {
+                    Self::write_rle_channel(channel, &mut out);
+                }
+            } else {
+                for color in row {
+                    out.extend_from_slice(&color_to_rgbe(color));
+                }
+            }
+            w.write_all(&out)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Shared exponent encoding: the largest component sets the exponent for all three.
+pub fn color_to_rgbe(color: &Color) -> [u8; 4] {
+    let r = color.x().max(0.0);
+    let g = color.y().max(0.0);
+    let b = color.z().max(0.0);
+    let v = r.max(g).max(b);
+
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // v = m * 2^e with m in [0.5, 1).
+    let e = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(e);
+    [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (e + 128).clamp(0, 255) as u8,
+    ]
+}
+
+pub fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    let f = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+// Reads a Radiance RGBE image with flat or run-length encoded scanlines in the standard
+// "-Y height +X width" orientation.
+pub fn decode(r: &mut dyn BufRead) -> io::Result<Image> {
+    let mut line = String::new();
+    r.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+
+    // Header variables run until the first blank line.
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            return Err(invalid_data("unexpected end of HDR header"));
+        }
+        let var = line.trim();
+        if var.is_empty() {
+            break;
+        }
+        if let Some(format) = var.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported HDR format {}", format)));
+            }
+        }
+    }
+
+    line.clear();
+    r.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => match (h.parse(), w.parse()) {
+            (Ok(h), Ok(w)) => (h, w),
+            _ => return Err(invalid_data("invalid HDR resolution")),
+        },
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported HDR orientation {:?}",
+                line.trim()
+            )))
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err(invalid_data("HDR image has an empty dimension"));
+    }
+    sample_count(width, height, 4)?;
+    let mut image = Image::new(width, height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        read_scanline(r, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set_pixel(x, y, rgbe_to_color(*rgbe));
+        }
+    }
+
+    Ok(image)
+}
+
+fn read_scanline(r: &mut dyn BufRead, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    let mut first = [0u8; 4];
+    r.read_exact(&mut first)?;
+
+    let is_rle = (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width)
+        && first[0] == 2
+        && first[1] == 2
+        && first[2] & 0x80 == 0;
+    if !is_rle {
+        scanline[0] = first;
+        for pixel in scanline.iter_mut().skip(1) {
+            r.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    if ((first[2] as usize) << 8 | first[3] as usize) != width {
+        return Err(invalid_data("HDR scanline width mismatch"));
+    }
+
+    for c in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            r.read_exact(&mut count)?;
+            let (run, literal) = if count[0] > 128 {
+                (count[0] as usize - 128, false)
+            } else {
+                (count[0] as usize, true)
+            };
+            if run == 0 || x + run > width {
+                return Err(invalid_data("corrupt HDR run length"));
+            }
+
+            if literal {
+                for pixel in &mut scanline[x..x + run] {
+                    r.read_exact(&mut count)?;
+                    pixel[c] = count[0];
+                }
+            } else {
+                r.read_exact(&mut count)?;
+                for pixel in &mut scanline[x..x + run] {
+                    pixel[c] = count[0];
+                }
+            }
+            x += run;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn decode_bytes(bytes: &[u8]) -> io::Result<Image> {
+        decode(&mut Cursor::new(bytes))
+    }
+
+    // Covers scanlines both with and without run-length encoding.
+    #[test]
+    fn round_trips() {
+        for width in [3, 10] {
+            let pixels = (0..width * 2)
+                .map(|i| match i % 3 {
+                    0 => Color::new(0.25, 0.25, 0.25),
+                    1 => Color::new(i as f64, 0.5, 0.0),
+                    _ => Color::new(0.01, 100.0, 3.0),
+                })
+                .collect();
+            let image = Image::from_pixels(width, 2, pixels);
+
+            let mut bytes = Vec::new();
+            HdrEncoder.encode(&image, &mut bytes).unwrap();
+            let decoded = decode_bytes(&bytes).unwrap();
+
+            assert_eq!((decoded.width(), decoded.height()), (width, 2));
+            for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+                // RGBE keeps 8 bits of mantissa relative to the brightest channel.
+                let largest = expected.x().max(expected.y()).max(expected.z());
+                for (e, a) in expected.e.iter().zip(actual.e) {
+                    assert!(
+                        (e - a).abs() <= largest / 128.0,
+                        "{:?} became {:?}",
+                        expected,
+                        actual
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_headers() {
+        for (height, width) in [(usize::MAX, 2), (1 << 20, 1 << 20)] {
+            let header = format!(
+                "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+                height, width
+            );
+            let err = decode_bytes(header.as_bytes()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_resolutions() {
+        for (height, width) in [(1, 0), (0, 1), (0, 0)] {
+            let mut bytes = format!(
+                "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+                height, width
+            )
+            .into_bytes();
+            bytes.extend_from_slice(&[2, 2, 0, 0]);
+            let err = decode_bytes(&bytes).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
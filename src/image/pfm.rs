@@ -0,0 +1,129 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::vec3::Color;
+
+use super::{invalid_data, read_token, sample_count, Encoder, Image};
+
+// Portable float map: unclamped linear RGB as little-endian f32, bottom row first.
+pub struct PfmEncoder;
+
+impl Encoder for PfmEncoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        // A negative scale marks the data as little-endian.
+        write!(w, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+        let mut row_bytes = Vec::with_capacity(image.width() * 12);
+        for row in image.rows().rev() {
+            row_bytes.clear();
+            for color in row {
+                for c in color.e {
+                    row_bytes.extend_from_slice(&(c as f32).to_le_bytes());
+                }
+            }
+            w.write_all(&row_bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Reads a color (PF) or greyscale (Pf) float map in either byte order.
+pub fn decode(r: &mut dyn BufRead) -> io::Result<Image> {
+    let channels = match read_token(r)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        magic => return Err(invalid_data(format!("not a PFM file: {:?}", magic))),
+    };
+    let width: usize = parse(&read_token(r)?)?;
+    let height: usize = parse(&read_token(r)?)?;
+    let scale: f32 = parse(&read_token(r)?)?;
+    let little_endian = scale < 0.0;
+
+    let size = sample_count(width, height, channels)? * 4;
+    let mut data = Vec::new();
+    r.take(size as u64).read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated PFM pixel data",
+        ));
+    }
+
+    let samples: Vec<f32> = data
+        .chunks_exact(4)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            }
+        })
+        .collect();
+
+    let mut image = Image::new(width, height);
+    for (i, pixel) in samples.chunks_exact(channels).enumerate() {
+        let color = match pixel {
+            [r, g, b] => Color::new(*r as f64, *g as f64, *b as f64),
+            [v] => Color::new(*v as f64, *v as f64, *v as f64),
+            _ => unreachable!(),
+        };
+        // Rows are stored bottom to top.
+        image.set_pixel(i % width, height - 1 - i / width, color);
+    }
+
+    Ok(image)
+}
+
+fn parse<T: std::str::FromStr>(token: &str) -> io::Result<T> {
+    token
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PFM header value {:?}", token)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn decode_bytes(bytes: &[u8]) -> io::Result<Image> {
+        decode(&mut Cursor::new(bytes))
+    }
+
+    #[test]
+    fn round_trips() {
+        let pixels = (0..6)
+            .map(|i| Color::new(i as f64, -0.5 * i as f64, 1e3 / (i + 1) as f64))
+            .collect();
+        let image = Image::from_pixels(3, 2, pixels);
+
+        let mut bytes = Vec::new();
+        PfmEncoder.encode(&image, &mut bytes).unwrap();
+        let decoded = decode_bytes(&bytes).unwrap();
+
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+            // Samples are stored as f32.
+            let rounded = expected.e.map(|c| c as f32 as f64);
+            assert_eq!(rounded, actual.e, "{:?} became {:?}", expected, actual);
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_headers() {
+        for header in [
+            format!("PF\n{} {}\n-1.0\n", usize::MAX, 2),
+            format!("PF\n{} {}\n-1.0\n", 1 << 20, 1 << 20),
+        ] {
+            let err = decode_bytes(header.as_bytes()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let err = decode_bytes(b"Pf\n4 4\n-1.0\n\0\0\0\0").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::{self, BufRead, Seek, Write};
+
+use png::{BitDepth, ColorType, ScaledFloat, Transformations};
+
+use crate::color::{bytes_to_color, color_to_bytes, color_to_words};
+
+use super::{invalid_data, Encoder, Image};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+// Gamma-corrected RGB PNG. The pixels use the same gamma 2 curve as the PPM output,
+// which is recorded in the file's gAMA chunk.
+#[derive(Default)]
+pub struct PngEncoder {
+    pub depth: PngDepth,
+}
+
+impl PngEncoder {
+    pub fn new(depth: PngDepth) -> Self {
+        Self { depth }
+    }
+}
+
+impl Encoder for PngEncoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(w, image.width() as u32, image.height() as u32);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_source_gamma(ScaledFloat::new(0.5));
+
+        let data = match self.depth {
+            PngDepth::Eight => {
+                encoder.set_depth(BitDepth::Eight);
+                image
+                    .pixels()
+                    .iter()
+                    .flat_map(|c| {
+                        let (r, g, b) = color_to_bytes(c);
+                        [r as u8, g as u8, b as u8]
+                    })
+                    .collect::<Vec<u8>>()
+            }
+            PngDepth::Sixteen => {
+                // PNG stores 16-bit samples big-endian.
+                encoder.set_depth(BitDepth::Sixteen);
+                image
+                    .pixels()
+                    .iter()
+                    .flat_map(|c| {
+                        let (r, g, b) = color_to_words(c);
+                        [r, g, b].into_iter().flat_map(u16::to_be_bytes)
+                    })
+                    .collect::<Vec<u8>>()
+            }
+        };
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&data).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+// Reads any PNG as 8-bit color, undoing the output gamma so the pixels are linear.
+// Alpha is dropped.
+pub fn decode<R: BufRead + Seek>(r: R) -> io::Result<Image> {
+    let mut decoder = png::Decoder::new(r);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid_data)?;
+
+    let mut data = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let info = reader.next_frame(&mut data).map_err(invalid_data)?;
+    let (color_type, _) = reader.output_color_type();
+    let channels = color_type.samples();
+
+    let pixels = data[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|p| match color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => bytes_to_color(p[0], p[0], p[0]),
+            _ => bytes_to_color(p[0], p[1], p[2]),
+        })
+        .collect();
+
+    Ok(Image::from_pixels(
+        info.width as usize,
+        info.height as usize,
+        pixels,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::vec3::Color;
+
+    fn test_image() -> Image {
+        let pixels = vec![
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.25, 0.5, 0.75),
+            Color::new(0.01, 2.0, -1.0),
+            Color::new(0.9, 0.1, 0.04),
+            Color::new(0.5, 0.5, 0.5),
+        ];
+        Image::from_pixels(3, 2, pixels)
+    }
+
+    // Encodes at `depth` and decodes the raw samples, checking the header on the way.
+    fn round_trip(depth: PngDepth, bit_depth: BitDepth) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        PngEncoder::new(depth)
+            .encode(&test_image(), &mut bytes)
+            .unwrap();
+
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.color_type, ColorType::Rgb);
+        assert_eq!(info.bit_depth, bit_depth);
+        assert_eq!(info.gama_chunk, Some(ScaledFloat::new(0.5)));
+
+        let mut data = vec![0; reader.output_buffer_size().unwrap()];
+        let frame = reader.next_frame(&mut data).unwrap();
+        data.truncate(frame.buffer_size());
+        data
+    }
+
+    #[test]
+    fn round_trips_eight_bit() {
+        let data = round_trip(PngDepth::Eight, BitDepth::Eight);
+        let expected: Vec<u8> = test_image()
+            .pixels()
+            .iter()
+            .flat_map(|c| {
+                let (r, g, b) = color_to_bytes(c);
+                [r as u8, g as u8, b as u8]
+            })
+            .collect();
+        assert_eq!(data, expected);
+        assert_eq!(&data[..6], &[0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn round_trips_sixteen_bit() {
+        let data = round_trip(PngDepth::Sixteen, BitDepth::Sixteen);
+        let words: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        let expected: Vec<u16> = test_image()
+            .pixels()
+            .iter()
+            .flat_map(|c| {
+                let (r, g, b) = color_to_words(c);
+                [r, g, b]
+            })
+            .collect();
+        assert_eq!(words, expected);
+        assert_eq!(&words[..6], &[0, 0, 0, 65535, 65535, 65535]);
+        assert_eq!(words[6], 32768);
+    }
+
+    #[test]
+    fn decodes_to_linear_color() {
+        let mut bytes = Vec::new();
+        PngEncoder::default()
+            .encode(&test_image(), &mut bytes)
+            .unwrap();
+        let image = decode(Cursor::new(bytes)).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+
+        let original = test_image();
+        for (decoded, expected) in image.pixels().iter().zip(original.pixels()) {
+            for axis in 0..3 {
+                let expected = expected.e[axis].clamp(0.0, 1.0);
+                assert!(
+                    (decoded.e[axis] - expected).abs() < 0.01,
+                    "decoded {:?} for {:?}",
+                    decoded,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{
+    color::{color_to_bytes, gamma_to_linear},
+    vec3::Color,
+};
+
+use super::{invalid_data, read_token, sample_count, Encoder, Image};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PpmFormat {
+    // Plain-text P3.
+    Ascii,
+    // Raw P6, one byte per sample.
+    #[default]
+    Binary,
+}
+
+// Gamma corrected PPM clamped to 8 bits.
+#[derive(Default)]
+pub struct PpmEncoder {
+    pub format: PpmFormat,
+}
+
+impl PpmEncoder {
+    pub fn new(format: PpmFormat) -> Self {
+        Self { format }
+    }
+
+    fn encode_ascii(image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "P3\n{} {}\n255", image.width(), image.height())?;
+
+        for row in image.rows() {
+            let mut row_str = String::with_capacity(row.len() * 12);
+            for color in row {
+                let (rbyte, gbyte, bbyte) = color_to_bytes(color);
+                row_str.push_str(&format!("{} {} {}\n", rbyte, gbyte, bbyte));
+            }
+            writeln!(w, "{}", row_str)?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_binary(image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", image.width(), image.height())?;
+
+        let mut row_bytes = Vec::with_capacity(image.width() * 3);
+        for row in image.rows() {
+            row_bytes.clear();
+            for color in row {
+                let (rbyte, gbyte, bbyte) = color_to_bytes(color);
+                row_bytes.extend_from_slice(&[rbyte as u8, gbyte as u8, bbyte as u8]);
+            }
+            w.write_all(&row_bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Encoder for PpmEncoder {
+    fn encode(&self, image: &Image, w: &mut dyn Write) -> io::Result<()> {
+        match self.format {
+            PpmFormat::Ascii => Self::encode_ascii(image, w),
+            PpmFormat::Binary => Self::encode_binary(image, w),
+        }
+    }
+}
+
+// Reads a P3 or P6 PPM with any maximum value, undoing the output gamma so the pixels
+// are linear.
+pub fn decode(r: &mut dyn BufRead) -> io::Result<Image> {
+    let format = match read_token(r)?.as_str() {
+        "P3" => PpmFormat::Ascii,
+        "P6" => PpmFormat::Binary,
+        magic => return Err(invalid_data(format!("unsupported PPM type {:?}", magic))),
+    };
+    let width = parse(&read_token(r)?)?;
+    let height = parse(&read_token(r)?)?;
+    let max_value = parse(&read_token(r)?)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data(format!("invalid PPM max value {}", max_value)));
+    }
+
+    let count = sample_count(width, height, 3)?;
+    let samples: Vec<usize> = match format {
+        PpmFormat::Ascii => {
+            let mut text = String::new();
+            r.read_to_string(&mut text)?;
+            text.split_whitespace()
+                .take(count)
+                .map(parse)
+                .collect::<io::Result<_>>()?
+        }
+        PpmFormat::Binary if max_value < 256 => {
+            let mut data = Vec::new();
+            r.take(count as u64).read_to_end(&mut data)?;
+            data.into_iter().map(usize::from).collect()
+        }
+        PpmFormat::Binary => {
+            let mut data = Vec::new();
+            r.take(count as u64 * 2).read_to_end(&mut data)?;
+            data.chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .collect()
+        }
+    };
+    if samples.len() != count {
+        return Err(invalid_data("truncated PPM pixel data"));
+    }
+
+    let to_linear = |s: usize| gamma_to_linear(s.min(max_value) as f64 / max_value as f64);
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|p| Color::new(to_linear(p[0]), to_linear(p[1]), to_linear(p[2])))
+        .collect();
+
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+fn parse(token: &str) -> io::Result<usize> {
+    token
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PPM value {:?}", token)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn decode_bytes(bytes: &[u8]) -> io::Result<Image> {
+        decode(&mut Cursor::new(bytes))
+    }
+
+    fn assert_pixels(image: &Image, expected: &[Color], tolerance: f64) {
+        assert_eq!(image.pixels().len(), expected.len());
+        for (expected, actual) in expected.iter().zip(image.pixels()) {
+            for (e, a) in expected.e.iter().zip(actual.e) {
+                assert!(
+                    (e - a).abs() <= tolerance,
+                    "{:?} became {:?}",
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_both_formats() {
+        let pixels = vec![
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.25, 0.5, 0.75),
+            Color::new(0.9, 0.1, 0.04),
+        ];
+        let image = Image::from_pixels(2, 2, pixels.clone());
+
+        for format in [PpmFormat::Ascii, PpmFormat::Binary] {
+            let mut bytes = Vec::new();
+            PpmEncoder::new(format).encode(&image, &mut bytes).unwrap();
+            let decoded = decode_bytes(&bytes).unwrap();
+
+            assert_eq!((decoded.width(), decoded.height()), (2, 2));
+            assert_pixels(&decoded, &pixels, 0.01);
+        }
+    }
+
+    #[test]
+    fn skips_header_comments() {
+        let mut bytes = b"P6\n# written by hand\n2 1 # width and height\n255\n".to_vec();
+        bytes.extend_from_slice(&[255, 0, 0, 0, 255, 0]);
+        let image = decode_bytes(&bytes).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        let expected = [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0)];
+        assert_pixels(&image, &expected, 0.0);
+    }
+
+    #[test]
+    fn rejects_oversized_headers() {
+        for header in [
+            format!("P6\n{} {}\n255\n", usize::MAX, 3),
+            format!("P6\n{} {}\n255\n", 1 << 20, 1 << 20),
+        ] {
+            let err = decode_bytes(header.as_bytes()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        for bytes in [
+            &b"P6\n2 2\n255\n\0\0\0"[..],
+            b"P6\n2 2\n65535\n\0\0\0",
+            b"P3\n2 2\n255\n0 0 0\n",
+        ] {
+            let err = decode_bytes(bytes).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use core::f64;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
@@ -19,6 +20,13 @@ impl Interval {
         Self { min, max }
     }
 
+    pub fn enclosing(a: &Interval, b: &Interval) -> Self {
+        Self {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
     pub fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -34,6 +42,11 @@ impl Interval {
     pub fn clamp(&self, x: f64) -> f64 {
         x.max(self.min).min(self.max)
     }
+
+    pub fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
 }
 
 pub const EMPTY: Interval = Interval {
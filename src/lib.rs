@@ -0,0 +1,18 @@
+pub mod aabb;
+pub mod camera;
+pub mod color;
+pub mod density;
+pub mod environment;
+pub mod hittable;
+pub mod image;
+pub mod interval;
+pub mod loader;
+pub mod material;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod ray;
+pub mod texture;
+pub mod transform;
+pub mod utility;
+pub mod vec3;
@@ -0,0 +1,103 @@
+use std::{error::Error, fmt::Display, io, path::Path, sync::Arc};
+
+use crate::{
+    hittable::HittableList,
+    material::{Lambertian, Material},
+    vec3::Color,
+};
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // A malformed statement, located by file and 1-based line number.
+    Parse {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    // Malformed data that has no line to point at, such as in a binary file.
+    Invalid {
+        file: String,
+        message: String,
+    },
+}
+
+impl LoadError {
+    fn parse<M: Into<String>>(file: &Path, line: usize, message: M) -> Self {
+        Self::Parse {
+            file: file.display().to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn invalid<M: Into<String>>(file: &Path, message: M) -> Self {
+        Self::Invalid {
+            file: file.display().to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+            Self::Invalid { file, message } => write!(f, "{}: {}", file, message),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse { .. } | Self::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// Loads a model, choosing the loader from the file extension of `path`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<HittableList, LoadError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    let mut list = HittableList::empty();
+    match extension.as_deref() {
+        Some("gltf" | "glb") => return Ok(gltf::load(path)?.world),
+        Some("obj") => return obj::load(path),
+        Some("ply") => list.add(Arc::new(ply::load(path)?)),
+        Some("stl") => list.add(Arc::new(stl::load(path)?)),
+        _ => {
+            return Err(LoadError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no model loader for {}", path.display()),
+            )))
+        }
+    }
+    Ok(list)
+}
+
+// Used for geometry whose file doesn't assign a material.
+pub(crate) fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)))
+}
@@ -0,0 +1,305 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use ::gltf::{
+    buffer::Data as BufferData,
+    camera::Projection,
+    image::{Data as ImageData, Format},
+    khr_lights_punctual::Kind,
+    mesh::Mode,
+    Node,
+};
+
+use crate::{
+    camera::{builder::CameraBuilder, Camera},
+    color::bytes_to_color,
+    hittable::{mesh::TriangleMesh, sphere::Sphere, HittableList},
+    image::Image,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    texture::{ImageTexture, SolidColor, Texture, TintedTexture},
+    transform::Transform,
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{default_material, LoadError};
+
+// Point and spot lights have no size in glTF, so they become small glowing spheres.
+const LIGHT_RADIUS: f64 = 0.05;
+
+// The contents of a glTF scene. Cameras come in document order and keep the render
+// settings of CameraBuilder's defaults apart from their placement and field of view.
+// Punctual lights are in `world` and also collected in `lights` for light sampling.
+pub struct GltfScene {
+    pub world: HittableList,
+    pub cameras: Vec<CameraBuilder>,
+    pub lights: HittableList,
+}
+
+// Loads the default scene (or the first one) of a `.gltf` or `.glb` file. Node transforms
+// are baked into the geometry, and materials are mapped from the metallic-roughness model
+// onto Lambertian, Metal, Dielectric and DiffuseLight.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<GltfScene, LoadError> {
+    let path = path.as_ref();
+    let (document, buffers, images) = ::gltf::import(path).map_err(|e| match e {
+        ::gltf::Error::Io(e) => LoadError::Io(e),
+        e => LoadError::invalid(path, e.to_string()),
+    })?;
+
+    let mut importer = Importer {
+        path,
+        buffers: &buffers,
+        images: &images,
+        materials: HashMap::new(),
+        scene: GltfScene {
+            world: HittableList::empty(),
+            cameras: Vec::new(),
+            lights: HittableList::empty(),
+        },
+    };
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| LoadError::invalid(path, "file has no scenes"))?;
+    for node in scene.nodes() {
+        importer.visit(&node, &Transform::IDENTITY)?;
+    }
+
+    Ok(importer.scene)
+}
+
+struct Importer<'a> {
+    path: &'a Path,
+    buffers: &'a [BufferData],
+    images: &'a [ImageData],
+    // Keyed by material index, with None for glTF's default material.
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    scene: GltfScene,
+}
+
+impl Importer<'_> {
+    fn visit(&mut self, node: &Node, parent: &Transform) -> Result<(), LoadError> {
+        // glTF matrices are column-major. A node that can't be inverted, such as one
+        // scaled to zero, collapses everything below it, so it's skipped.
+        let columns = node.transform().matrix();
+        let matrix = [0, 1, 2, 3].map(|r| columns.map(|column| column[r] as f64));
+        let Some(local) = Transform::from_matrix(matrix) else {
+            return Ok(());
+        };
+        let transform = local.then(parent);
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                self.add_primitive(&primitive, &transform)?;
+            }
+        }
+        if let Some(camera) = node.camera() {
+            self.add_camera(&camera, &transform);
+        }
+        if let Some(light) = node.light() {
+            self.add_light(&light, &transform);
+        }
+
+        for child in node.children() {
+            self.visit(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    fn add_primitive(
+        &mut self,
+        primitive: &::gltf::Primitive,
+        transform: &Transform,
+    ) -> Result<(), LoadError> {
+        // Points and lines have no surface to render.
+        if primitive.mode() != Mode::Triangles {
+            return Ok(());
+        }
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            return Ok(());
+        };
+
+        let positions: Vec<Point3> = positions
+            .map(|[x, y, z]| transform.apply_point(&to_vec3([x, y, z])))
+            .collect();
+        let normals = reader.read_normals().map(|normals| {
+            normals
+                .map(|n| transform.apply_normal(&to_vec3(n)))
+                .collect::<Vec<_>>()
+        });
+        // The base color texture names the UV set it's mapped with. glTF puts the UV origin
+        // at the top left; textures here expect it at the bottom.
+        let tex_coord = primitive
+            .material()
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .map_or(0, |info| info.tex_coord());
+        let uvs = reader.read_tex_coords(tex_coord).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                .collect::<Vec<_>>()
+        });
+
+        let flat: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if let Some(&index) = flat.iter().find(|&&i| i >= positions.len()) {
+            return Err(LoadError::invalid(
+                self.path,
+                format!(
+                    "primitive {} index {} out of range ({} vertices)",
+                    primitive.index(),
+                    index,
+                    positions.len()
+                ),
+            ));
+        }
+        // Mirroring transforms reverse the winding, so swap two corners to keep faces
+        // pointing the same way.
+        let mirrored = transform.determinant() < 0.0;
+        let indices: Vec<[usize; 3]> = flat
+            .chunks_exact(3)
+            .map(|t| {
+                if mirrored {
+                    [t[0], t[2], t[1]]
+                } else {
+                    [t[0], t[1], t[2]]
+                }
+            })
+            .collect();
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        let normals = normals.filter(|n| n.len() == positions.len());
+        let uvs = uvs.filter(|uv| uv.len() == positions.len());
+        let material = self.material(&primitive.material());
+        self.scene.world.add(Arc::new(TriangleMesh::new(
+            positions, indices, normals, uvs, material,
+        )));
+        Ok(())
+    }
+
+    fn material(&mut self, material: &::gltf::Material) -> Arc<dyn Material> {
+        let images = self.images;
+        self.materials
+            .entry(material.index())
+            .or_insert_with(|| convert_material(material, images))
+            .clone()
+    }
+
+    // glTF cameras look down their local -Z axis with +Y up.
+    fn add_camera(&mut self, camera: &::gltf::Camera, transform: &Transform) {
+        // Orthographic cameras have no equivalent here.
+        let Projection::Perspective(perspective) = camera.projection() else {
+            return;
+        };
+
+        let lookfrom = transform.apply_point(&Point3::new(0.0, 0.0, 0.0));
+        let forward = transform.apply_vector(&Vec3::new(0.0, 0.0, -1.0));
+        let mut builder = Camera::builder()
+            .vfov((perspective.yfov() as f64).to_degrees())
+            .lookfrom(lookfrom)
+            .lookat(lookfrom + Vec3::unit_vector(&forward))
+            .vup(transform.apply_vector(&Vec3::new(0.0, 1.0, 0.0)));
+        if let Some(aspect_ratio) = perspective.aspect_ratio() {
+            builder = builder.aspect_ratio(aspect_ratio as f64);
+        }
+        self.scene.cameras.push(builder);
+    }
+
+    // Spot lights are treated as point lights, and directional lights are skipped since
+    // they have no position to place an emitter at.
+    fn add_light(&mut self, light: &::gltf::khr_lights_punctual::Light, transform: &Transform) {
+        if let Kind::Directional = light.kind() {
+            return;
+        }
+
+        // A diffuse sphere of radiance L has intensity L·πr² in every direction, so this
+        // matches the light's intensity in candela.
+        let intensity =
+            light.intensity() as f64 / (std::f64::consts::PI * LIGHT_RADIUS * LIGHT_RADIUS);
+        let center = transform.apply_point(&Point3::new(0.0, 0.0, 0.0));
+        let sphere = Arc::new(Sphere::new(
+            center,
+            LIGHT_RADIUS,
+            DiffuseLight::new(to_vec3(light.color()) * intensity),
+        ));
+        self.scene.world.add(sphere.clone());
+        self.scene.lights.add(sphere);
+    }
+}
+
+fn convert_material(material: &::gltf::Material, images: &[ImageData]) -> Arc<dyn Material> {
+    if material.index().is_none() {
+        return default_material();
+    }
+
+    let emission =
+        to_vec3(material.emissive_factor()) * material.emissive_strength().unwrap_or(1.0) as f64;
+    if !emission.near_zero() {
+        return Arc::new(DiffuseLight::new(emission));
+    }
+
+    if material
+        .transmission()
+        .is_some_and(|t| t.transmission_factor() > 0.0)
+    {
+        return Arc::new(Dielectric::new(material.ior().unwrap_or(1.5) as f64));
+    }
+
+    // The base color factor scales the texture's color when there is one.
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let factor = Color::new(r as f64, g as f64, b as f64);
+    let base_color: Arc<dyn Texture> = match pbr
+        .base_color_texture()
+        .and_then(|info| images.get(info.texture().source().index()))
+        .and_then(to_image)
+    {
+        Some(image) if (factor - Color::new(1.0, 1.0, 1.0)).near_zero() => {
+            Arc::new(ImageTexture::new(image))
+        }
+        Some(image) => Arc::new(TintedTexture::new(
+            Arc::new(ImageTexture::new(image)),
+            factor,
+        )),
+        None => Arc::new(SolidColor::new(factor)),
+    };
+
+    // glTF materials are metallic unless they say otherwise.
+    if pbr.metallic_factor() >= 0.5 {
+        let fuzz = pbr.roughness_factor() as f64;
+        return Arc::new(Metal::from_texture(base_color, fuzz));
+    }
+    Arc::new(Lambertian::from_texture(base_color))
+}
+
+// Converts 8-bit sRGB image data to a linear image; other formats aren't supported.
+fn to_image(data: &ImageData) -> Option<Image> {
+    let channels = match data.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        _ => return None,
+    };
+    let pixels = data
+        .pixels
+        .chunks_exact(channels)
+        .map(|p| match p.len() {
+            1 | 2 => bytes_to_color(p[0], p[0], p[0]),
+            _ => bytes_to_color(p[0], p[1], p[2]),
+        })
+        .collect();
+    Some(Image::from_pixels(
+        data.width as usize,
+        data.height as usize,
+        pixels,
+    ))
+}
+
+fn to_vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(x as f64, y as f64, z as f64)
+}
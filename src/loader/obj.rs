@@ -0,0 +1,394 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    hittable::{mesh::TriangleMesh, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    texture::{ImageTexture, Texture},
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{default_material, LoadError};
+
+// Loads a Wavefront OBJ file into one triangle mesh per material. Polygons are
+// triangulated as fans, and `mtllib` files are resolved relative to the OBJ file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<HittableList, LoadError> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut state = ObjState::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| LoadError::parse(path, index + 1, e.to_string()))?;
+        state
+            .parse_line(&line, path)
+            .map_err(|e| e.at(path, index + 1))?;
+    }
+
+    Ok(state.finish())
+}
+
+// Error raised while parsing a single line, before it is given a location.
+enum LineError {
+    Message(String),
+    Load(LoadError),
+}
+
+impl LineError {
+    fn at(self, path: &Path, line: usize) -> LoadError {
+        match self {
+            Self::Message(message) => LoadError::parse(path, line, message),
+            Self::Load(e) => e,
+        }
+    }
+}
+
+impl From<String> for LineError {
+    fn from(message: String) -> Self {
+        Self::Message(message)
+    }
+}
+
+impl From<LoadError> for LineError {
+    fn from(e: LoadError) -> Self {
+        Self::Load(e)
+    }
+}
+
+// Triangles sharing one material, with vertices deduplicated by their
+// position/uv/normal index triple.
+struct Group {
+    material: Arc<dyn Material>,
+    positions: Vec<Point3>,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vec3>>,
+    indices: Vec<[usize; 3]>,
+    vertex_lookup: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+}
+
+impl Group {
+    fn new(material: Arc<dyn Material>) -> Self {
+        Self {
+            material,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+            vertex_lookup: HashMap::new(),
+        }
+    }
+
+    fn into_mesh(self) -> TriangleMesh {
+        // Meshes carry normals and uvs for every vertex or for none.
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>();
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>();
+        TriangleMesh::new(self.positions, self.indices, normals, uvs, self.material)
+    }
+}
+
+#[derive(Default)]
+struct ObjState {
+    positions: Vec<Point3>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    groups: Vec<Group>,
+    group_lookup: HashMap<String, usize>,
+    current_group: Option<usize>,
+}
+
+impl ObjState {
+    fn parse_line(&mut self, line: &str, path: &Path) -> Result<(), LineError> {
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            return Ok(());
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let p = parse_floats(&args, 3, 3)?;
+                self.positions.push(Point3::new(p[0], p[1], p[2]));
+            }
+            "vt" => {
+                let uv = parse_floats(&args, 1, 2)?;
+                self.uvs.push((uv[0], uv.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let n = parse_floats(&args, 3, 3)?;
+                self.normals.push(Vec3::new(n[0], n[1], n[2]));
+            }
+            "f" => self.parse_face(&args)?,
+            "mtllib" => {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                for name in args {
+                    let mtl_path = dir.join(name);
+                    let file = File::open(&mtl_path).map_err(|e| {
+                        format!("can't open material library {}: {}", mtl_path.display(), e)
+                    })?;
+                    self.materials.extend(load_mtl(&mtl_path, file)?);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                let material = self
+                    .materials
+                    .get(&name)
+                    .ok_or_else(|| format!("unknown material '{}'", name))?
+                    .clone();
+                self.current_group = Some(self.group_index(name, material));
+            }
+            // Comments, object/group names, smoothing groups and other statements
+            // don't affect the output.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_face(&mut self, args: &[&str]) -> Result<(), String> {
+        if args.len() < 3 {
+            return Err(format!(
+                "face needs at least 3 vertices, got {}",
+                args.len()
+            ));
+        }
+
+        let vertices = args
+            .iter()
+            .map(|arg| self.parse_face_vertex(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let group_index = match self.current_group {
+            Some(index) => index,
+            None => {
+                let index = self.group_index(String::new(), default_material());
+                self.current_group = Some(index);
+                index
+            }
+        };
+        let group = &mut self.groups[group_index];
+
+        let local: Vec<usize> = vertices
+            .into_iter()
+            .map(|key @ (p, uv, n)| {
+                *group.vertex_lookup.entry(key).or_insert_with(|| {
+                    group.positions.push(self.positions[p]);
+                    group.uvs.push(uv.map(|i| self.uvs[i]));
+                    group.normals.push(n.map(|i| self.normals[i]));
+                    group.positions.len() - 1
+                })
+            })
+            .collect();
+
+        for i in 1..local.len() - 1 {
+            group.indices.push([local[0], local[i], local[i + 1]]);
+        }
+
+        Ok(())
+    }
+
+    // Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based indices.
+    fn parse_face_vertex(
+        &self,
+        arg: &str,
+    ) -> Result<(usize, Option<usize>, Option<usize>), String> {
+        let mut parts = arg.split('/');
+        let position = resolve_index(parts.next(), self.positions.len(), "vertex")?
+            .ok_or_else(|| format!("face vertex '{}' has no position index", arg))?;
+        let uv = resolve_index(parts.next(), self.uvs.len(), "texture coordinate")?;
+        let normal = resolve_index(parts.next(), self.normals.len(), "normal")?;
+        if parts.next().is_some() {
+            return Err(format!("malformed face vertex '{}'", arg));
+        }
+        Ok((position, uv, normal))
+    }
+
+    fn group_index(&mut self, name: String, material: Arc<dyn Material>) -> usize {
+        *self.group_lookup.entry(name).or_insert_with(|| {
+            self.groups.push(Group::new(material));
+            self.groups.len() - 1
+        })
+    }
+
+    fn finish(self) -> HittableList {
+        let mut list = HittableList::empty();
+        for group in self.groups {
+            if !group.indices.is_empty() {
+                list.add(Arc::new(group.into_mesh()));
+            }
+        }
+        list
+    }
+}
+
+// OBJ indices are 1-based, and negative values count back from the latest element.
+fn resolve_index(token: Option<&str>, count: usize, kind: &str) -> Result<Option<usize>, String> {
+    let Some(token) = token.filter(|t| !t.is_empty()) else {
+        return Ok(None);
+    };
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("invalid {} index '{}'", kind, token))?;
+
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => count as i64 + i,
+        _ => return Err(format!("{} index must not be 0", kind)),
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "{} index {} out of range ({} defined)",
+            kind, index, count
+        ));
+    }
+    Ok(Some(resolved as usize))
+}
+
+// Parses between `min` and `max` numbers, ignoring any further arguments.
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min {
+        return Err(format!(
+            "expected at least {} numbers, got {}",
+            min,
+            args.len()
+        ));
+    }
+    args.iter()
+        .take(max)
+        .map(|a| a.parse().map_err(|_| format!("invalid number '{}'", a)))
+        .collect()
+}
+
+fn parse_color(args: &[&str]) -> Result<Color, String> {
+    let values = parse_floats(args, 1, 3)?;
+    // A single value stands for a grey.
+    Ok(match values[..] {
+        [r, g, b] => Color::new(r, g, b),
+        _ => Color::new(values[0], values[0], values[0]),
+    })
+}
+
+#[derive(Default)]
+struct MtlEntry {
+    diffuse: Option<Color>,
+    specular: Option<Color>,
+    emission: Option<Color>,
+    refraction_index: Option<f64>,
+    shininess: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<i32>,
+    diffuse_map: Option<Arc<dyn Texture>>,
+}
+
+impl MtlEntry {
+    fn into_material(self) -> Arc<dyn Material> {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let diffuse = self.diffuse.unwrap_or(Color::new(0.8, 0.8, 0.8));
+        let specular = self.specular.unwrap_or(black);
+        let emission = self.emission.unwrap_or(black);
+        let illum = self.illum.unwrap_or(2);
+
+        if !emission.near_zero() {
+            return Arc::new(DiffuseLight::new(emission));
+        }
+
+        // Illumination models 4, 6, 7 and 9 are the transparent ones.
+        let transparent = self.dissolve.is_some_and(|d| d < 1.0) || matches!(illum, 4 | 6 | 7 | 9);
+        if transparent {
+            return Arc::new(Dielectric::new(self.refraction_index.unwrap_or(1.5)));
+        }
+
+        // Model 3 is a mirror; otherwise a purely specular material is treated as metal.
+        let mirror = illum == 3 || (diffuse.near_zero() && self.diffuse_map.is_none());
+        if mirror && !specular.near_zero() {
+            // Maps the Phong exponent onto the roughness of a matching microfacet lobe.
+            let fuzz = (2.0 / (self.shininess.unwrap_or(0.0) + 2.0)).sqrt();
+            return Arc::new(Metal::new(specular, fuzz));
+        }
+
+        match self.diffuse_map {
+            Some(texture) => Arc::new(Lambertian::from_texture(texture)),
+            None => Arc::new(Lambertian::new(diffuse)),
+        }
+    }
+}
+
+fn load_mtl(path: &Path, file: File) -> Result<HashMap<String, Arc<dyn Material>>, LoadError> {
+    let reader = BufReader::new(file);
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlEntry)> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let at = |message: String| LoadError::parse(path, index + 1, message);
+        let line = line.map_err(|e| at(e.to_string()))?;
+
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, entry)) = current.take() {
+                materials.insert(name, entry.into_material());
+            }
+            if args.is_empty() {
+                return Err(at(String::from("newmtl needs a material name")));
+            }
+            current = Some((args.join(" "), MtlEntry::default()));
+            continue;
+        }
+        if keyword.starts_with('#') {
+            continue;
+        }
+
+        let Some((_, entry)) = current.as_mut() else {
+            return Err(at(format!("'{}' before any newmtl", keyword)));
+        };
+        match keyword {
+            "Kd" => entry.diffuse = Some(parse_color(&args).map_err(at)?),
+            "Ks" => entry.specular = Some(parse_color(&args).map_err(at)?),
+            "Ke" => entry.emission = Some(parse_color(&args).map_err(at)?),
+            "Ni" => entry.refraction_index = Some(parse_floats(&args, 1, 1).map_err(at)?[0]),
+            "Ns" => entry.shininess = Some(parse_floats(&args, 1, 1).map_err(at)?[0]),
+            "d" => entry.dissolve = Some(parse_floats(&args, 1, 1).map_err(at)?[0]),
+            "Tr" => entry.dissolve = Some(1.0 - parse_floats(&args, 1, 1).map_err(at)?[0]),
+            "illum" => {
+                let value = args.first().copied().unwrap_or_default();
+                entry.illum = Some(
+                    value
+                        .parse()
+                        .map_err(|_| at(format!("invalid illumination model '{}'", value)))?,
+                );
+            }
+            "map_Kd" => {
+                // Texture options come before the file name, which is the last token.
+                let Some(name) = args.last() else {
+                    return Err(at(String::from("map_Kd needs a file name")));
+                };
+                let texture_path: PathBuf = dir.join(name);
+                let texture = ImageTexture::load(&texture_path).map_err(|e| {
+                    at(format!(
+                        "failed to load texture {}: {}",
+                        texture_path.display(),
+                        e
+                    ))
+                })?;
+                entry.diffuse_map = Some(Arc::new(texture));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, entry)) = current {
+        materials.insert(name, entry.into_material());
+    }
+    Ok(materials)
+}
@@ -0,0 +1,435 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    color::bytes_to_color,
+    hittable::mesh::TriangleMesh,
+    material::{Lambertian, Material},
+    vec3::{Color, Point3, Vec3},
+};
+
+use super::{default_material, LoadError};
+
+// Loads an ASCII or binary PLY file as a single mesh with the default material, or a
+// white one when the file has vertex colors so they show as they are.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, LoadError> {
+    let mesh = read(path.as_ref())?;
+    let mat: Arc<dyn Material> = if mesh.has_colors() {
+        Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)))
+    } else {
+        default_material()
+    };
+    Ok(mesh.build(mat))
+}
+
+// Loads a PLY file with `mat`, tinted by the vertex colors if the file has them.
+pub fn load_with_material<P: AsRef<Path>>(
+    path: P,
+    mat: Arc<dyn Material>,
+) -> Result<TriangleMesh, LoadError> {
+    Ok(read(path.as_ref())?.build(mat))
+}
+
+fn read(path: &Path) -> Result<MeshBuilder, LoadError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let header = Header::read(&mut reader, path)?;
+    let mut body = match header.format {
+        Format::Ascii => Body::Ascii {
+            reader: &mut reader,
+            line: header.lines,
+            tokens: Vec::new(),
+        },
+        Format::BinaryLittleEndian => Body::Binary {
+            reader: &mut reader,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            reader: &mut reader,
+            big_endian: true,
+        },
+    };
+
+    let mut mesh = MeshBuilder::default();
+    let mut values = Vec::new();
+    for element in &header.elements {
+        for index in 0..element.count {
+            body.read_record(element, &mut values).map_err(|message| {
+                body.error(path, format!("{} {}: {}", element.name, index, message))
+            })?;
+            let added = match element.name.as_str() {
+                "vertex" => mesh.add_vertex(element, &values),
+                "face" => mesh.add_face(element, &values),
+                _ => Ok(()),
+            };
+            added.map_err(|message| {
+                body.error(path, format!("{} {}: {}", element.name, index, message))
+            })?;
+        }
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| names.contains(&p.name.as_str()))
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    // Number of lines in the header, so ASCII body errors can report file lines.
+    lines: usize,
+}
+
+impl Header {
+    fn read(reader: &mut dyn BufRead, path: &Path) -> Result<Self, LoadError> {
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let mut lines = 0;
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(LoadError::parse(path, lines, "missing end_header"));
+            }
+            lines += 1;
+            let at = |message: String| LoadError::parse(path, lines, message);
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if lines == 1 {
+                if tokens != ["ply"] {
+                    return Err(at(String::from("not a PLY file")));
+                }
+                continue;
+            }
+
+            match tokens[..] {
+                ["end_header"] => break,
+                ["format", kind, _version] => {
+                    format = Some(match kind {
+                        "ascii" => Format::Ascii,
+                        "binary_little_endian" => Format::BinaryLittleEndian,
+                        "binary_big_endian" => Format::BinaryBigEndian,
+                        _ => return Err(at(format!("unknown format '{}'", kind))),
+                    });
+                }
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| at(format!("invalid element count '{}'", count)))?,
+                    properties: Vec::new(),
+                }),
+                ["property", ..] => {
+                    let Some(element) = elements.last_mut() else {
+                        return Err(at(String::from("property before any element")));
+                    };
+                    let scalar = |name: &str| {
+                        ScalarType::parse(name)
+                            .ok_or_else(|| at(format!("unknown property type '{}'", name)))
+                    };
+                    let (kind, name) = match tokens[1..] {
+                        ["list", count, item, name] => (
+                            PropertyKind::List {
+                                count: scalar(count)?,
+                                item: scalar(item)?,
+                            },
+                            name,
+                        ),
+                        [ty, name] => (PropertyKind::Scalar(scalar(ty)?), name),
+                        _ => return Err(at(String::from("malformed property"))),
+                    };
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        kind,
+                    });
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => return Err(at(format!("unexpected header line '{}'", line.trim()))),
+            }
+        }
+
+        let format = format.ok_or_else(|| LoadError::parse(path, lines, "missing format"))?;
+        Ok(Self {
+            format,
+            elements,
+            lines,
+        })
+    }
+}
+
+enum Body<'a> {
+    // Each element instance is one line of whitespace-separated numbers.
+    Ascii {
+        reader: &'a mut dyn BufRead,
+        line: usize,
+        tokens: Vec<String>,
+    },
+    Binary {
+        reader: &'a mut dyn BufRead,
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    // Reads one element instance into `values`: each scalar property as one value, and
+    // each list property as its length followed by its items.
+    fn read_record(&mut self, element: &Element, values: &mut Vec<f64>) -> Result<(), String> {
+        values.clear();
+        if let Self::Ascii {
+            reader,
+            line,
+            tokens,
+        } = self
+        {
+            let mut text = String::new();
+            if reader.read_line(&mut text).map_err(|e| e.to_string())? == 0 {
+                return Err(String::from("unexpected end of file"));
+            }
+            *line += 1;
+            *tokens = text.split_whitespace().rev().map(String::from).collect();
+        }
+
+        for property in &element.properties {
+            match property.kind {
+                PropertyKind::Scalar(ty) => values.push(self.read_scalar(ty)?),
+                PropertyKind::List { count, item } => {
+                    let n = self.read_scalar(count)?;
+                    values.push(n);
+                    for _ in 0..n as usize {
+                        values.push(self.read_scalar(item)?);
+                    }
+                }
+            }
+        }
+
+        match self {
+            Self::Ascii { tokens, .. } if !tokens.is_empty() => {
+                Err(String::from("too many values"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn read_scalar(&mut self, ty: ScalarType) -> Result<f64, String> {
+        match self {
+            Self::Ascii { tokens, .. } => {
+                let token = tokens.pop().ok_or("too few values")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", token))
+            }
+            Self::Binary { reader, big_endian } => {
+                let mut buf = [0u8; 8];
+                let bytes = &mut buf[..ty.size()];
+                reader.read_exact(bytes).map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => String::from("unexpected end of file"),
+                    _ => e.to_string(),
+                })?;
+                if *big_endian {
+                    bytes.reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buf[0] as i8 as f64,
+                    ScalarType::U8 => buf[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+
+    fn error(&self, path: &Path, message: String) -> LoadError {
+        match self {
+            Self::Ascii { line, .. } => LoadError::parse(path, *line, message),
+            Self::Binary { .. } => LoadError::invalid(path, message),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    colors: Vec<Color>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    fn add_vertex(&mut self, element: &Element, values: &[f64]) -> Result<(), String> {
+        if element
+            .properties
+            .iter()
+            .any(|p| matches!(p.kind, PropertyKind::List { .. }))
+        {
+            return Err(String::from(
+                "list properties on vertices are not supported",
+            ));
+        }
+        let get = |names: &[&str]| element.property(names).map(|i| values[i]);
+        let get3 = |a, b, c| Some(Vec3::new(get(a)?, get(b)?, get(c)?));
+
+        self.positions
+            .push(get3(&["x"], &["y"], &["z"]).ok_or("vertex needs x, y and z")?);
+        if let Some(n) = get3(&["nx"], &["ny"], &["nz"]) {
+            self.normals.push(n);
+        }
+        let u = get(&["u", "s", "texture_u", "texture_s"]);
+        let v = get(&["v", "t", "texture_v", "texture_t"]);
+        if let (Some(u), Some(v)) = (u, v) {
+            self.uvs.push((u, v));
+        }
+
+        let (Some(r), Some(g), Some(b)) = (
+            element.property(&["red", "r"]),
+            element.property(&["green", "g"]),
+            element.property(&["blue", "b"]),
+        ) else {
+            return Ok(());
+        };
+        // 8-bit colors are gamma encoded like image files; float colors are linear.
+        self.colors.push(match element.properties[r].kind {
+            PropertyKind::Scalar(ScalarType::U8) => {
+                bytes_to_color(values[r] as u8, values[g] as u8, values[b] as u8)
+            }
+            _ => Color::new(values[r], values[g], values[b]),
+        });
+        Ok(())
+    }
+
+    fn add_face(&mut self, element: &Element, values: &[f64]) -> Result<(), String> {
+        let target = element
+            .property(&["vertex_indices", "vertex_index"])
+            .ok_or("face needs a vertex_indices list")?;
+
+        // Skip over the preceding properties to find where the index list starts.
+        let mut offset = 0;
+        for property in &element.properties[..target] {
+            offset += match property.kind {
+                PropertyKind::Scalar(_) => 1,
+                PropertyKind::List { .. } => 1 + values[offset] as usize,
+            };
+        }
+        let count = values[offset] as usize;
+        let polygon = &values[offset + 1..offset + 1 + count];
+        if count < 3 {
+            return Err(format!("face needs at least 3 vertices, got {}", count));
+        }
+
+        let vertex_count = self.positions.len();
+        let mut resolved = Vec::with_capacity(count);
+        for &index in polygon {
+            if index < 0.0 || index as usize >= vertex_count {
+                return Err(format!(
+                    "vertex index {} out of range ({} defined)",
+                    index, vertex_count
+                ));
+            }
+            resolved.push(index as usize);
+        }
+
+        for i in 1..count - 1 {
+            self.indices
+                .push([resolved[0], resolved[i], resolved[i + 1]]);
+        }
+        Ok(())
+    }
+
+    fn has_colors(&self) -> bool {
+        !self.colors.is_empty() && self.colors.len() == self.positions.len()
+    }
+
+    fn build(self, mat: Arc<dyn Material>) -> TriangleMesh {
+        let has_colors = self.has_colors();
+        let vertex_count = self.positions.len();
+        let normals = (self.normals.len() == vertex_count).then_some(self.normals);
+        let uvs = (self.uvs.len() == vertex_count).then_some(self.uvs);
+
+        if !has_colors {
+            return TriangleMesh::new(self.positions, self.indices, normals, uvs, mat);
+        }
+        TriangleMesh::with_vertex_colors(
+            self.positions,
+            self.indices,
+            normals,
+            uvs,
+            self.colors,
+            mat,
+        )
+    }
+}
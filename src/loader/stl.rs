@@ -0,0 +1,114 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::{hittable::mesh::TriangleMesh, material::Material, vec3::Point3};
+
+use super::{default_material, LoadError};
+
+// Loads an ASCII or binary STL file as a single mesh with the default material. STL
+// stores every triangle separately, so coincident vertices are merged.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, LoadError> {
+    load_with_material(path, default_material())
+}
+
+pub fn load_with_material<P: AsRef<Path>>(
+    path: P,
+    mat: Arc<dyn Material>,
+) -> Result<TriangleMesh, LoadError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+
+    // Binary files may also start with "solid", so the size check comes first.
+    let triangles = if is_binary(&data) {
+        parse_binary(&data)
+    } else if data.starts_with(b"solid") {
+        parse_ascii(&data, path)?
+    } else {
+        return Err(LoadError::invalid(path, "not an STL file"));
+    };
+
+    let mut positions = Vec::new();
+    let mut lookup = HashMap::new();
+    let indices = triangles
+        .into_iter()
+        .map(|triangle| {
+            triangle.map(|p| {
+                let key = [p.x().to_bits(), p.y().to_bits(), p.z().to_bits()];
+                *lookup.entry(key).or_insert_with(|| {
+                    positions.push(p);
+                    positions.len() - 1
+                })
+            })
+        })
+        .collect();
+
+    Ok(TriangleMesh::new(positions, indices, None, None, mat))
+}
+
+// An 80-byte header and a triangle count, followed by 50 bytes per triangle.
+fn is_binary(data: &[u8]) -> bool {
+    data.len() >= 84 && {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        data.len() == 84 + count * 50
+    }
+}
+
+fn parse_binary(data: &[u8]) -> Vec<[Point3; 3]> {
+    // Each record is a facet normal, three vertices and a 2-byte attribute; the normal
+    // is ignored in favour of the vertex winding.
+    data[84..]
+        .chunks_exact(50)
+        .map(|record| {
+            let value = |offset: usize| {
+                let bytes = [
+                    record[offset],
+                    record[offset + 1],
+                    record[offset + 2],
+                    record[offset + 3],
+                ];
+                f32::from_le_bytes(bytes) as f64
+            };
+            let point =
+                |offset: usize| Point3::new(value(offset), value(offset + 4), value(offset + 8));
+            [point(12), point(24), point(36)]
+        })
+        .collect()
+}
+
+fn parse_ascii(data: &[u8], path: &Path) -> Result<Vec<[Point3; 3]>, LoadError> {
+    let text = std::str::from_utf8(data).map_err(|e| LoadError::invalid(path, e.to_string()))?;
+
+    let mut triangles = Vec::new();
+    let mut vertices = Vec::with_capacity(3);
+    for (index, line) in text.lines().enumerate() {
+        let at = |message: String| LoadError::parse(path, index + 1, message);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens[..] {
+            ["vertex", x, y, z] => {
+                let parse = |s: &str| {
+                    s.parse::<f64>()
+                        .map_err(|_| at(format!("invalid number '{}'", s)))
+                };
+                if vertices.len() == 3 {
+                    return Err(at(String::from("facet has more than 3 vertices")));
+                }
+                vertices.push(Point3::new(parse(x)?, parse(y)?, parse(z)?));
+            }
+            ["vertex", ..] => return Err(at(String::from("vertex needs 3 coordinates"))),
+            ["endloop"] => {
+                let [a, b, c] = vertices[..] else {
+                    return Err(at(format!(
+                        "facet needs 3 vertices, got {}",
+                        vertices.len()
+                    )));
+                };
+                triangles.push([a, b, c]);
+                vertices.clear();
+            }
+            // solid, facet normal, outer loop, endfacet and endsolid carry nothing we use.
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
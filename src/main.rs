@@ -1,22 +1,37 @@
-mod camera;
-mod color;
-mod hittable;
-mod interval;
-mod material;
-mod ray;
-mod utility;
-mod vec3;
-
+use std::path::Path;
 use std::time::Instant;
 use std::{f64::consts::PI, sync::Arc};
 
-use camera::Camera;
-use hittable::sphere::Sphere;
-use hittable::HittableList;
-use material::{Dielectric, Lambertian, Metal};
-use vec3::{Color, Point3};
+use raytracing_iow::aabb::Aabb;
+use raytracing_iow::camera::{builder::CameraBuilder, Aovs, Camera};
+use raytracing_iow::density::{Density, GridDensity, NoiseDensity};
+use raytracing_iow::environment::ConstantEnvironment;
+use raytracing_iow::hittable::bvh::BvhNode;
+use raytracing_iow::hittable::constant_medium::ConstantMedium;
+use raytracing_iow::hittable::heterogeneous_medium::HeterogeneousMedium;
+use raytracing_iow::hittable::instance::Instance;
+use raytracing_iow::hittable::mesh::TriangleMesh;
+use raytracing_iow::hittable::quad::{make_box, Quad};
+use raytracing_iow::hittable::sphere::Sphere;
+use raytracing_iow::hittable::triangle::Triangle;
+use raytracing_iow::hittable::{Hittable, HittableList};
+use raytracing_iow::image::lowercase_extension;
+use raytracing_iow::loader::{self, gltf};
+use raytracing_iow::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use raytracing_iow::texture::{CheckerTexture, MarbleTexture, WoodTexture};
+use raytracing_iow::transform::Transform;
+use raytracing_iow::vec3::{Color, Point3, Vec3};
+
+fn default_camera() -> CameraBuilder {
+    Camera::builder()
+        .image_width(400)
+        .aspect_ratio(16.0 / 9.0)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(90.0)
+}
 
-fn three_spheres_scene() -> HittableList {
+fn three_spheres_scene() -> (HittableList, CameraBuilder) {
     let material_ground = Lambertian::new(Color::new(0.8, 0.8, 0.0));
     let material_center = Lambertian::new(Color::new(0.1, 0.2, 0.5));
     let material_left = Dielectric::new(1.5);
@@ -50,10 +65,10 @@ fn three_spheres_scene() -> HittableList {
             material_right,
         )),
     ];
-    HittableList::from(spheres)
+    (HittableList::from(spheres), default_camera())
 }
 
-fn two_spheres_scene() -> HittableList {
+fn two_spheres_scene() -> (HittableList, CameraBuilder) {
     let material_left = Lambertian::new(Color::new(0.0, 0.0, 1.0));
     let material_right = Lambertian::new(Color::new(1.0, 0.0, 0.0));
 
@@ -63,27 +78,513 @@ fn two_spheres_scene() -> HittableList {
         Arc::new(Sphere::new(Point3::new(-r, 0.0, -1.0), r, material_left)),
         Arc::new(Sphere::new(Point3::new(r, 0.0, -1.0), r, material_right)),
     ];
-    HittableList::from(spheres)
+    (HittableList::from(spheres), default_camera())
+}
+
+fn checkered_spheres_scene() -> (HittableList, CameraBuilder) {
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.32,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+
+    let spheres = vec![
+        Arc::new(Sphere::new(
+            Point3::new(0.0, -10.0, 0.0),
+            10.0,
+            Lambertian::from_texture(checker.clone()),
+        )),
+        Arc::new(Sphere::new(
+            Point3::new(0.0, 10.0, 0.0),
+            10.0,
+            Lambertian::from_texture(checker),
+        )),
+    ];
+
+    let camera = default_camera()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0));
+    (HittableList::from(spheres), camera)
+}
+
+fn perlin_spheres_scene() -> (HittableList, CameraBuilder) {
+    let marble = MarbleTexture::new(4.0, Color::new(1.0, 1.0, 1.0));
+    let wood = WoodTexture::new(6.0, Color::new(0.8, 0.6, 0.35), Color::new(0.4, 0.25, 0.1));
+
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian::from_texture(Arc::new(marble)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Lambertian::from_texture(Arc::new(wood)),
+    )));
+
+    let camera = default_camera()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0));
+    (world, camera)
+}
+
+fn quads_scene() -> (HittableList, CameraBuilder) {
+    let left_red = Arc::new(Lambertian::new(Color::new(1.0, 0.2, 0.2)));
+    let back_green = Arc::new(Lambertian::new(Color::new(0.2, 1.0, 0.2)));
+    let right_blue = Arc::new(Lambertian::new(Color::new(0.2, 0.2, 1.0)));
+    let upper_orange = Arc::new(Lambertian::new(Color::new(1.0, 0.5, 0.0)));
+    let lower_teal = Arc::new(Lambertian::new(Color::new(0.2, 0.8, 0.8)));
+
+    let quads = vec![
+        Arc::new(Quad::new(
+            Point3::new(-3.0, -2.0, 5.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            left_red,
+        )),
+        Arc::new(Quad::new(
+            Point3::new(-2.0, -2.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            back_green,
+        )),
+        Arc::new(Quad::new(
+            Point3::new(3.0, -2.0, 1.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            right_blue,
+        )),
+        Arc::new(Quad::new(
+            Point3::new(-2.0, 3.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            upper_orange,
+        )),
+        Arc::new(Quad::new(
+            Point3::new(-2.0, -3.0, 5.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            lower_teal,
+        )),
+    ];
+
+    let camera = default_camera()
+        .aspect_ratio(1.0)
+        .vfov(80.0)
+        .lookfrom(Point3::new(0.0, 0.0, 9.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0));
+    (HittableList::from(quads), camera)
+}
+
+fn triangles_scene() -> (HittableList, CameraBuilder) {
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Quad::new(
+        Point3::new(-4.0, -1.0, -4.0),
+        Vec3::new(8.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 8.0),
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+    world.add(Arc::new(Triangle::new(
+        Point3::new(-2.5, -1.0, -1.0),
+        Point3::new(-0.5, -1.0, -1.0),
+        Point3::new(-1.5, 0.8, -1.5),
+        Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))),
+    )));
+
+    // An octahedron with vertex normals pointing away from its center shades like a sphere.
+    let positions = vec![
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(-1.0, 0.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(0.0, -1.0, 0.0),
+        Point3::new(0.0, 0.0, 1.0),
+        Point3::new(0.0, 0.0, -1.0),
+    ];
+    let normals = positions.clone();
+    let indices = vec![
+        [0, 2, 4],
+        [4, 2, 1],
+        [1, 2, 5],
+        [5, 2, 0],
+        [4, 3, 0],
+        [1, 3, 4],
+        [5, 3, 1],
+        [0, 3, 5],
+    ];
+    let offset = Vec3::new(1.2, 0.0, -1.0);
+    let positions = positions.into_iter().map(|p| p + offset).collect();
+    world.add(Arc::new(TriangleMesh::new(
+        positions,
+        indices,
+        Some(normals),
+        None,
+        Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    )));
+
+    let camera = default_camera()
+        .lookfrom(Point3::new(0.0, 1.5, 3.0))
+        .lookat(Point3::new(0.0, 0.0, -1.0))
+        .vfov(50.0);
+    (world, camera)
+}
+
+fn motion_blur_scene() -> (HittableList, CameraBuilder) {
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.5,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian::from_texture(checker),
+    )));
+    world.add(Arc::new(Sphere::moving(
+        Point3::new(-2.2, 0.6, 0.0),
+        Point3::new(-2.2, 1.4, 0.0),
+        0.6,
+        Lambertian::new(Color::new(0.8, 0.3, 0.2)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 0.6, 0.0),
+        0.6,
+        Metal::new(Color::new(0.8, 0.8, 0.8), 0.05),
+    )));
+
+    let cube = make_box(
+        Point3::new(-0.5, 0.0, -0.5),
+        Point3::new(0.5, 1.0, 0.5),
+        Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    );
+    world.add(Arc::new(
+        Instance::new(
+            Arc::new(cube),
+            Transform::rotate_y(30.0).then(&Transform::translate(Vec3::new(2.0, 0.0, 0.0))),
+        )
+        .with_motion(Vec3::new(0.0, 0.0, 0.8)),
+    ));
+
+    let camera = default_camera()
+        .vfov(30.0)
+        .lookfrom(Point3::new(0.0, 3.0, 9.0))
+        .lookat(Point3::new(0.0, 0.6, 0.0))
+        .shutter(0.0, 1.0);
+    (world, camera)
+}
+
+// The five walls of the Cornell box, lit by `light`.
+fn cornell_room(light: Arc<Quad>) -> HittableList {
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green,
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    )));
+    world.add(light);
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white,
+    )));
+    world
+}
+
+// The tall and short boxes of the Cornell box, rotated into place.
+fn cornell_boxes(mat: Arc<dyn Material>) -> (Instance, Instance) {
+    let tall_box = make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        mat.clone(),
+    );
+    let short_box = make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        mat,
+    );
+    (
+        Instance::new(
+            Arc::new(tall_box),
+            Transform::rotate_y(15.0).then(&Transform::translate(Vec3::new(265.0, 0.0, 295.0))),
+        ),
+        Instance::new(
+            Arc::new(short_box),
+            Transform::rotate_y(-18.0).then(&Transform::translate(Vec3::new(130.0, 0.0, 65.0))),
+        ),
+    )
+}
+
+fn cornell_camera() -> CameraBuilder {
+    default_camera()
+        .aspect_ratio(1.0)
+        .samples_per_pixel(200)
+        .vfov(40.0)
+        .lookfrom(Point3::new(278.0, 278.0, -800.0))
+        .lookat(Point3::new(278.0, 278.0, 0.0))
+        .environment(Arc::new(ConstantEnvironment::new(Color::new(
+            0.0, 0.0, 0.0,
+        ))))
+}
+
+fn cornell_box_scene() -> (HittableList, CameraBuilder) {
+    let light = Arc::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0))),
+    ));
+    let mut world = cornell_room(light.clone());
+
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let (tall_box, short_box) = cornell_boxes(white);
+    world.add(Arc::new(tall_box));
+    world.add(Arc::new(short_box));
+
+    (world, cornell_camera().lights(light))
+}
+
+// The Cornell box with its boxes replaced by dark and light smoke.
+fn cornell_smoke_scene() -> (HittableList, CameraBuilder) {
+    let light = Arc::new(Quad::new(
+        Point3::new(113.0, 554.0, 127.0),
+        Vec3::new(330.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 305.0),
+        Arc::new(DiffuseLight::new(Color::new(7.0, 7.0, 7.0))),
+    ));
+    let mut world = cornell_room(light.clone());
+
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let (tall_box, short_box) = cornell_boxes(white);
+    world.add(Arc::new(ConstantMedium::from_color(
+        Arc::new(tall_box),
+        0.01,
+        Color::new(0.0, 0.0, 0.0),
+    )));
+    world.add(Arc::new(ConstantMedium::from_color(
+        Arc::new(short_box),
+        0.01,
+        Color::new(1.0, 1.0, 1.0),
+    )));
+
+    (world, cornell_camera().lights(light))
+}
+
+// A white cloud scattering mostly forwards next to an absorbing tinted one, both
+// shaped by noise inside spherical boundaries.
+fn clouds_scene() -> (HittableList, CameraBuilder) {
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+    )));
+
+    let noise: Arc<dyn Density> = Arc::new(NoiseDensity::new(1.5, 4.0));
+    let cloud = HeterogeneousMedium::new(
+        Arc::new(Sphere::new(
+            Point3::new(-1.3, 1.2, 0.0),
+            1.2,
+            Lambertian::default(),
+        )),
+        noise.clone(),
+        Color::new(0.0, 0.0, 0.0),
+        Color::new(1.0, 1.0, 1.0),
+    )
+    .with_anisotropy(0.6);
+    world.add(Arc::new(cloud));
+    world.add(Arc::new(HeterogeneousMedium::new(
+        Arc::new(Sphere::new(
+            Point3::new(1.3, 1.2, 0.0),
+            1.2,
+            Lambertian::default(),
+        )),
+        noise,
+        Color::new(0.1, 0.6, 1.2),
+        Color::new(0.4, 0.3, 0.2),
+    )));
+
+    let camera = default_camera()
+        .vfov(30.0)
+        .lookfrom(Point3::new(0.0, 2.0, 9.0))
+        .lookat(Point3::new(0.0, 1.0, 0.0));
+    (world, camera)
+}
+
+// Renders a Mitsuba `.vol` density grid as white smoke over a floor.
+fn volume_grid_scene(path: &str) -> (HittableList, CameraBuilder) {
+    let grid = GridDensity::load(path).unwrap_or_else(|e| panic!("failed to load {}: {}", path, e));
+    let bbox = grid.bounding_box();
+
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Quad::new(
+        Point3::new(-1000.0, bbox.y.min, -1000.0),
+        Vec3::new(2000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2000.0),
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+    let boundary = make_box(
+        Point3::new(bbox.x.min, bbox.y.min, bbox.z.min),
+        Point3::new(bbox.x.max, bbox.y.max, bbox.z.max),
+        Arc::new(Lambertian::default()),
+    );
+    world.add(Arc::new(HeterogeneousMedium::new(
+        Arc::new(boundary),
+        Arc::new(grid),
+        Color::new(0.05, 0.05, 0.05),
+        Color::new(1.0, 1.0, 1.0),
+    )));
+
+    (world, frame_model(&bbox))
+}
+
+fn glowing_spheres_scene() -> (HittableList, CameraBuilder) {
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Lambertian::new(Color::new(0.2, 0.4, 0.8)),
+    )));
+    let light = Arc::new(Sphere::new(
+        Point3::new(0.0, 7.0, 0.0),
+        1.5,
+        DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+    ));
+    world.add(light.clone());
+
+    let camera = default_camera()
+        .lights(light)
+        .vfov(20.0)
+        .lookfrom(Point3::new(26.0, 3.0, 6.0))
+        .lookat(Point3::new(0.0, 2.0, 0.0))
+        .environment(Arc::new(ConstantEnvironment::new(Color::new(
+            0.0, 0.0, 0.0,
+        ))));
+    (world, camera)
+}
+
+// Looks at a model of the given bounds from the front and slightly above.
+fn frame_model(bbox: &Aabb) -> CameraBuilder {
+    let center = Point3::new(bbox.centroid(0), bbox.centroid(1), bbox.centroid(2));
+    let radius = (Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()) * 0.5).length();
+
+    default_camera()
+        .vfov(40.0)
+        .lookfrom(center + Vec3::new(0.6, 0.5, 1.0) * (2.5 * radius))
+        .lookat(center)
+}
+
+// Shows a loaded mesh over a grey floor.
+fn model_scene(path: &str) -> (HittableList, CameraBuilder) {
+    let model = loader::load(path).unwrap_or_else(|e| panic!("failed to load {}: {}", path, e));
+
+    let bbox = model.bounding_box();
+    let center = Point3::new(bbox.centroid(0), bbox.centroid(1), bbox.centroid(2));
+    let radius = (Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()) * 0.5).length();
+
+    let mut world = HittableList::empty();
+    world.add(Arc::new(Quad::new(
+        Point3::new(
+            center.x() - 50.0 * radius,
+            bbox.y.min,
+            center.z() - 50.0 * radius,
+        ),
+        Vec3::new(100.0 * radius, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0 * radius),
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+    world.add(Arc::new(model));
+
+    (world, frame_model(&bbox))
+}
+
+// Renders a glTF scene through its first camera, or framed like a model if it has none.
+fn gltf_scene(path: &str) -> (HittableList, CameraBuilder) {
+    let scene = gltf::load(path).unwrap_or_else(|e| panic!("failed to load {}: {}", path, e));
+
+    let mut camera = match scene.cameras.into_iter().next() {
+        Some(camera) => camera,
+        None => frame_model(&scene.world.bounding_box()),
+    };
+    if !scene.lights.is_empty() {
+        camera = camera.lights(Arc::new(scene.lights));
+    }
+    (scene.world, camera)
 }
 
 fn main() {
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width = 400;
+    let (scene, camera) = match std::env::args().nth(1).as_deref() {
+        Some("two-spheres") => two_spheres_scene(),
+        Some("checkered-spheres") => checkered_spheres_scene(),
+        Some("perlin-spheres") => perlin_spheres_scene(),
+        Some("glowing-spheres") => glowing_spheres_scene(),
+        Some("quads") => quads_scene(),
+        Some("triangles") => triangles_scene(),
+        Some("motion-blur") => motion_blur_scene(),
+        Some("cornell-box") => cornell_box_scene(),
+        Some("cornell-smoke") => cornell_smoke_scene(),
+        Some("clouds") => clouds_scene(),
+        Some(path) if path.ends_with(".vol") => volume_grid_scene(path),
+        Some(path) if path.ends_with(".gltf") || path.ends_with(".glb") => gltf_scene(path),
+        Some(path) if Path::new(path).extension().is_some() => model_scene(path),
+        _ => three_spheres_scene(),
+    };
+    let world = BvhNode::new(scene);
 
-    let world = three_spheres_scene();
-    //let world = Arc::new(world);
+    let output = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| String::from("output.ppm"));
 
     let start = Instant::now();
-    let mut camera = Camera::new(image_width, aspect_ratio, 100, 50, 90.0);
-    camera.render(&world);
-    let duration = start.elapsed();
+    let camera = camera.build().expect("invalid camera settings");
+    // EXR output carries every first-hit pass along with the beauty image.
+    let (duration, saved) = if lowercase_extension(Path::new(&output)).as_deref() == Some("exr") {
+        let layers = camera.render_layers(&world, Aovs::all());
+        (start.elapsed(), layers.save(&output))
+    } else {
+        let image = camera.render(&world);
+        (start.elapsed(), image.save(&output))
+    };
+    saved.unwrap_or_else(|e| panic!("failed to write {}: {}", output, e));
 
     println!("Done!");
     println!(
         "Rendered {} rays at {}x{} resolution in: {:?}",
-        camera.size_of_image() * camera.samples_per_pixel,
-        camera.image_width,
-        camera.image_height,
+        camera.size_of_image() * camera.samples_per_pixel(),
+        camera.image_width(),
+        camera.image_height(),
         duration
     );
 }
@@ -1,77 +1,148 @@
+use std::{f64::consts::PI, sync::Arc};
+
 use crate::{
     hittable::HitRecord,
+    pdf::{CosinePdf, HenyeyGreensteinPdf, Pdf, ScatterPdf, SpherePdf},
     ray::Ray,
+    texture::{SolidColor, Texture},
     utility::random_double,
-    vec3::{self, dot, reflect, Color, Vec3},
+    vec3::{self, dot, reflect, Color, Point3, Vec3},
 };
 
+// How a material scatters an incoming ray. Diffuse materials give a density to draw
+// the new direction from. Specular ones set `skip_pdf` and give the one ray they
+// scatter into as `skip_pdf_ray`.
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub pdf: ScatterPdf,
+    pub skip_pdf: bool,
+    pub skip_pdf_ray: Ray,
+}
+
+impl Default for ScatterRecord {
+    fn default() -> Self {
+        Self {
+            attenuation: Color::default(),
+            pdf: ScatterPdf::Sphere(SpherePdf),
+            skip_pdf: false,
+            skip_pdf_ray: Ray::default(),
+        }
+    }
+}
+
+impl ScatterRecord {
+    pub fn specular(attenuation: Color, ray: Ray) -> Self {
+        Self {
+            attenuation,
+            skip_pdf: true,
+            skip_pdf_ray: ray,
+            ..Self::default()
+        }
+    }
+}
+
 pub trait Material: Send + Sync {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
+        false
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // The BSDF times the cosine term for scattering into `scattered`, divided by the
+    // attenuation. It need not match the density `scatter` samples from, which only
+    // affects noise. Specular materials leave it at 0.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    // Surface color reported in the albedo render layer.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
 }
 
-#[derive(Default, Clone)]
+// A surface texture's color at a hit, tinted by the mesh's vertex colors if it has any.
+fn surface_color(tex: &dyn Texture, rec: &HitRecord) -> Color {
+    let color = tex.value(rec.u, rec.v, &rec.p);
+    match rec.vertex_color {
+        Some(tint) => color * tint,
+        None => color,
+    }
+}
+
+#[derive(Clone)]
 pub struct Lambertian {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
-        Self { albedo }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Default for Lambertian {
+    fn default() -> Self {
+        Self::new(Color::default())
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-        *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = self.albedo;
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = surface_color(self.tex.as_ref(), rec);
+        srec.pdf = CosinePdf::new(&rec.normal).into();
+        srec.skip_pdf = false;
 
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = dot(&rec.normal, &Vec3::unit_vector(&scattered.direction()));
+        cos_theta.max(0.0) / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        surface_color(self.tex.as_ref(), rec)
+    }
 }
 
 pub struct Metal {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
     fuzz: f64,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, fuzz: f64) -> Self {
         Self {
-            albedo,
+            tex,
             fuzz: fuzz.min(1.0),
         }
     }
 }
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
         let mut reflected = reflect(&r_in.direction(), &rec.normal);
         reflected = Vec3::unit_vector(&reflected) + (Vec3::random_unit_vector() * self.fuzz);
-        *scattered = Ray::new(rec.p, reflected);
-        *attenuation = self.albedo;
+        *srec = ScatterRecord::specular(
+            surface_color(self.tex.as_ref(), rec),
+            Ray::with_time(rec.p, reflected, r_in.time()),
+        );
 
-        dot(&scattered.direction(), &rec.normal) > 0.0
+        dot(&reflected, &rec.normal) > 0.0
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        surface_color(self.tex.as_ref(), rec)
     }
 }
 
@@ -93,14 +164,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
         let ri = match rec.front_face {
             true => 1.0 / self.refraction_index,
             false => self.refraction_index,
@@ -109,16 +173,109 @@ impl Material for Dielectric {
         let unit_direction = Vec3::unit_vector(&r_in.direction());
         let cos_theta = dot(&-unit_direction, &rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-        let direction: Vec3;
+        let direction =
+            if sin_theta * ri > 1.0 || Dielectric::reflectance(cos_theta, ri) > random_double() {
+                vec3::reflect(&unit_direction, &rec.normal)
+            } else {
+                vec3::refract(&unit_direction, &rec.normal, ri)
+            };
+
+        *srec = ScatterRecord::specular(
+            Color::new(1.0, 1.0, 1.0),
+            Ray::with_time(rec.p, direction, r_in.time()),
+        );
+
+        true
+    }
+}
+
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.tex.value(u, v, p)
+    }
+}
+
+// Phase function of a participating medium that scatters equally in every direction.
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
 
-        if sin_theta * ri > 1.0 || Dielectric::reflectance(cos_theta, ri) > random_double() {
-            direction = vec3::reflect(&unit_direction, &rec.normal);
-        } else {
-            direction = vec3::refract(&unit_direction, &rec.normal, ri);
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        srec.pdf = SpherePdf.into();
+        srec.skip_pdf = false;
+
+        true
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+// Henyey–Greenstein phase function. The asymmetry `g` in (-1, 1) favours forward
+// scattering when positive, backward when negative, and is isotropic at 0.
+pub struct HenyeyGreenstein {
+    g: f64,
+    tex: Arc<dyn Texture>,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64, albedo: Color) -> Self {
+        Self::from_texture(g, Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(g: f64, tex: Arc<dyn Texture>) -> Self {
+        Self {
+            g: g.clamp(-0.999, 0.999),
+            tex,
         }
+    }
+}
 
-        *scattered = Ray::new(rec.p, direction);
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        srec.pdf = HenyeyGreensteinPdf::new(&r_in.direction(), self.g).into();
+        srec.skip_pdf = false;
 
         true
     }
+
+    fn scattering_pdf(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> f64 {
+        HenyeyGreensteinPdf::new(&r_in.direction(), self.g).value(&scattered.direction())
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
 }
@@ -0,0 +1,93 @@
+use crate::vec3::{cross, Vec3};
+
+// An orthonormal basis whose w axis points along a given direction, for turning
+// directions sampled around +Z into world space.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    pub fn new(n: &Vec3) -> Self {
+        let w = Vec3::unit_vector(n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::unit_vector(&cross(&w, &a));
+        let u = cross(&w, &v);
+
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    // Maps local coordinates along (u, v, w) to world space.
+    pub fn transform(&self, local: &Vec3) -> Vec3 {
+        self.axis[0] * local.x() + self.axis[1] * local.y() + self.axis[2] * local.z()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::dot;
+
+    fn assert_orthonormal(n: &Vec3) {
+        let uvw = Onb::new(n);
+        for axis in [uvw.u(), uvw.v(), uvw.w()] {
+            assert!((axis.length() - 1.0).abs() < 1e-9, "{:?} is not unit", axis);
+        }
+        assert!(dot(&uvw.u(), &uvw.v()).abs() < 1e-9);
+        assert!(dot(&uvw.v(), &uvw.w()).abs() < 1e-9);
+        assert!(dot(&uvw.w(), &uvw.u()).abs() < 1e-9);
+        assert!(
+            (uvw.w() - Vec3::unit_vector(n)).near_zero(),
+            "w is not along {:?}",
+            n
+        );
+    }
+
+    #[test]
+    fn axis_aligned_inputs() {
+        for n in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -3.0),
+        ] {
+            assert_orthonormal(&n);
+        }
+    }
+
+    #[test]
+    fn arbitrary_inputs() {
+        for _ in 0..1000 {
+            let n = Vec3::random_range(-5.0, 5.0);
+            if n.length() > 1e-3 {
+                assert_orthonormal(&n);
+            }
+        }
+    }
+
+    #[test]
+    fn transform_maps_local_axes() {
+        let uvw = Onb::new(&Vec3::new(0.3, -2.0, 0.7));
+        assert!((uvw.transform(&Vec3::new(1.0, 0.0, 0.0)) - uvw.u()).near_zero());
+        assert!((uvw.transform(&Vec3::new(0.0, 1.0, 0.0)) - uvw.v()).near_zero());
+        assert!((uvw.transform(&Vec3::new(0.0, 0.0, 1.0)) - uvw.w()).near_zero());
+    }
+}
@@ -0,0 +1,244 @@
+use std::f64::consts::PI;
+
+use crate::{
+    onb::Onb,
+    utility::random_double,
+    vec3::{dot, Vec3},
+};
+
+// A distribution of directions to scatter in, with the solid angle density of each.
+pub trait Pdf {
+    fn value(&self, direction: &Vec3) -> f64;
+
+    fn generate(&self) -> Vec3;
+}
+
+// Every direction is equally likely.
+#[derive(Debug, Clone, Copy)]
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self) -> Vec3 {
+        Vec3::random_unit_vector()
+    }
+}
+
+// Directions in the hemisphere around a normal, weighted by the cosine to it.
+#[derive(Debug, Clone, Copy)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3) -> Self {
+        Self { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine_theta = dot(&Vec3::unit_vector(direction), &self.uvw.w());
+        cosine_theta.max(0.0) / PI
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.transform(&random_cosine_direction())
+    }
+}
+
+// Directions around the incoming one, following the Henyey–Greenstein phase function
+// with asymmetry `g` in (-1, 1).
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreensteinPdf {
+    uvw: Onb,
+    g: f64,
+}
+
+impl HenyeyGreensteinPdf {
+    pub fn new(incoming: &Vec3, g: f64) -> Self {
+        Self {
+            uvw: Onb::new(incoming),
+            g,
+        }
+    }
+}
+
+impl Pdf for HenyeyGreensteinPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cos_theta = dot(&Vec3::unit_vector(direction), &self.uvw.w());
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    fn generate(&self) -> Vec3 {
+        let g = self.g;
+        let xi = random_double();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * random_double();
+
+        self.uvw.transform(&Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+// The densities materials scatter with, held by value so that filling in a
+// ScatterRecord doesn't allocate.
+#[derive(Debug, Clone, Copy)]
+pub enum ScatterPdf {
+    Sphere(SpherePdf),
+    Cosine(CosinePdf),
+    HenyeyGreenstein(HenyeyGreensteinPdf),
+}
+
+impl Pdf for ScatterPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        match self {
+            Self::Sphere(pdf) => pdf.value(direction),
+            Self::Cosine(pdf) => pdf.value(direction),
+            Self::HenyeyGreenstein(pdf) => pdf.value(direction),
+        }
+    }
+
+    fn generate(&self) -> Vec3 {
+        match self {
+            Self::Sphere(pdf) => pdf.generate(),
+            Self::Cosine(pdf) => pdf.generate(),
+            Self::HenyeyGreenstein(pdf) => pdf.generate(),
+        }
+    }
+}
+
+impl From<SpherePdf> for ScatterPdf {
+    fn from(pdf: SpherePdf) -> Self {
+        Self::Sphere(pdf)
+    }
+}
+
+impl From<CosinePdf> for ScatterPdf {
+    fn from(pdf: CosinePdf) -> Self {
+        Self::Cosine(pdf)
+    }
+}
+
+impl From<HenyeyGreensteinPdf> for ScatterPdf {
+    fn from(pdf: HenyeyGreensteinPdf) -> Self {
+        Self::HenyeyGreenstein(pdf)
+    }
+}
+
+// A unit direction around +Z with density cos(theta) / pi.
+pub fn random_cosine_direction() -> Vec3 {
+    let r1 = random_double();
+    let r2 = random_double();
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+
+    Vec3::new(x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 200_000;
+
+    fn mean(f: impl Fn() -> f64) -> f64 {
+        (0..SAMPLES).map(|_| f()).sum::<f64>() / SAMPLES as f64
+    }
+
+    // The density integrates to one over the sphere, estimated with uniform directions.
+    fn assert_normalized(pdf: &dyn Pdf) {
+        let integral = 4.0 * PI * mean(|| pdf.value(&Vec3::random_unit_vector()));
+        assert!((integral - 1.0).abs() < 0.03, "integrates to {}", integral);
+    }
+
+    // E[1 / value(X)] for X drawn by generate() is the solid angle the density covers.
+    fn assert_consistent(pdf: &dyn Pdf, support: f64) {
+        let estimate = mean(|| 1.0 / pdf.value(&pdf.generate()));
+        let error = (estimate - support).abs() / support;
+        assert!(error < 0.02, "estimated {} for {}", estimate, support);
+    }
+
+    #[test]
+    fn sphere_pdf() {
+        assert_normalized(&SpherePdf);
+        assert_consistent(&SpherePdf, 4.0 * PI);
+        for _ in 0..1000 {
+            assert!((SpherePdf.generate().length() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn cosine_pdf() {
+        let normal = Vec3::new(1.0, 2.0, -0.5);
+        let pdf = CosinePdf::new(&normal);
+        assert_normalized(&pdf);
+        for _ in 0..1000 {
+            let direction = pdf.generate();
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            assert!(dot(&direction, &normal) >= 0.0);
+        }
+
+        // 1 / value blows up at the horizon, so weigh by cos^2 instead, whose integral
+        // over the hemisphere is 2 pi / 3.
+        let w = Vec3::unit_vector(&normal);
+        let estimate = mean(|| {
+            let direction = pdf.generate();
+            dot(&direction, &w).powi(2) / pdf.value(&direction)
+        });
+        assert!(
+            (estimate - 2.0 * PI / 3.0).abs() < 0.01,
+            "estimated {}",
+            estimate
+        );
+    }
+
+    #[test]
+    fn henyey_greenstein_pdf() {
+        let incoming = Vec3::new(0.0, -1.0, 1.0);
+        let w = Vec3::unit_vector(&incoming);
+        for g in [1e-4, 0.3, -0.3, 0.6, -0.6] {
+            let pdf = HenyeyGreensteinPdf::new(&incoming, g);
+            assert_normalized(&pdf);
+            assert_consistent(&pdf, 4.0 * PI);
+
+            // The asymmetry is the mean cosine to the incoming direction.
+            let mean_cos = mean(|| dot(&pdf.generate(), &w));
+            assert!(
+                (mean_cos - g).abs() < 0.01,
+                "mean cosine {} for g = {}",
+                mean_cos,
+                g
+            );
+        }
+    }
+
+    #[test]
+    fn scatter_pdf_delegates() {
+        let cosine = CosinePdf::new(&Vec3::new(0.0, 0.0, 1.0));
+        let pdf = ScatterPdf::from(cosine);
+        let direction = Vec3::new(0.2, 0.1, 0.9);
+        assert_eq!(pdf.value(&direction), cosine.value(&direction));
+        assert_eq!(
+            ScatterPdf::from(SpherePdf).value(&direction),
+            1.0 / (4.0 * PI)
+        );
+    }
+}
@@ -4,11 +4,22 @@ use crate::vec3::Point3;
 pub struct Ray {
     origin: Point3,
     dir: Point3,
+    time: f64,
 }
 
 impl Ray {
     pub fn new(o: Point3, d: Point3) -> Self {
-        Self { origin: o, dir: d }
+        Self::with_time(o, d, 0.0)
+    }
+
+    // A ray at a moment within the camera shutter, which moving objects use to place
+    // themselves.
+    pub fn with_time(o: Point3, d: Point3, time: f64) -> Self {
+        Self {
+            origin: o,
+            dir: d,
+            time,
+        }
     }
 
     pub fn origin(&self) -> Point3 {
@@ -17,6 +28,9 @@ impl Ray {
     pub fn direction(&self) -> Point3 {
         self.dir
     }
+    pub fn time(&self) -> f64 {
+        self.time
+    }
 
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + self.dir * t
@@ -0,0 +1,211 @@
+use std::{io, path::Path, sync::Arc};
+
+use crate::{
+    image::Image,
+    perlin::Perlin,
+    vec3::{Color, Point3},
+};
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+#[derive(Default)]
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+
+    pub fn from_rgb(red: f64, green: f64, blue: f64) -> Self {
+        Self::new(Color::new(red, green, blue))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+// Alternates between two textures on a 3D lattice of cubes `scale` units wide.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(c1)),
+            Arc::new(SolidColor::new(c2)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// Looks up surface UVs in an image, with v = 0 at the bottom row.
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Self {
+        Self { image }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(Image::load(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // A missing image shows up as solid cyan, which is easy to spot.
+        if self.image.pixels().is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        self.image.sample(u, 1.0 - v.clamp(0.0, 1.0))
+    }
+}
+
+// Another texture multiplied by a constant color.
+pub struct TintedTexture {
+    tex: Arc<dyn Texture>,
+    tint: Color,
+}
+
+impl TintedTexture {
+    pub fn new(tex: Arc<dyn Texture>, tint: Color) -> Self {
+        Self { tex, tint }
+    }
+}
+
+impl Texture for TintedTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.tex.value(u, v, p) * self.tint
+    }
+}
+
+// Smooth Perlin noise remapped to [0, 1] grey.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        Color::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + self.noise.noise(&(*p * self.scale)))
+    }
+}
+
+// Seven octaves of turbulence as grey, giving a cloudy, camouflage-like pattern.
+pub struct TurbulenceTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl TurbulenceTexture {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        Color::new(1.0, 1.0, 1.0) * self.noise.turb(&(*p * self.scale), 7)
+    }
+}
+
+// Veins running along Z, where a sine stripe pattern is phase shifted by turbulence.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f64,
+    color: Color,
+}
+
+impl MarbleTexture {
+    pub fn new(scale: f64, color: Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            color,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let phase = self.scale * p.z() + 10.0 * self.noise.turb(p, 7);
+        self.color * 0.5 * (1.0 + phase.sin())
+    }
+}
+
+// Concentric growth rings around the Y axis, `scale` rings per unit, warped by
+// turbulence so they wobble like real grain.
+pub struct WoodTexture {
+    noise: Perlin,
+    scale: f64,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    pub fn new(scale: f64, light: Color, dark: Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let radius = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let rings = self.scale * radius + 0.6 * self.noise.turb(p, 4);
+        // Sharpen each ring so the dark late-wood band is thinner than the light one.
+        let t = (rings - rings.floor()).powf(3.0);
+        self.light * (1.0 - t) + self.dark * t
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    aabb::Aabb,
+    ray::Ray,
+    utility::degrees_to_radians,
+    vec3::{cross, dot, Point3, Vec3},
+};
+
+// An affine transform, kept together with its inverse. Matrices are row-major and act
+// on column vectors, with the translation in the last column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: [[f64; 4]; 4],
+    inv: [[f64; 4]; 4],
+}
+
+const IDENTITY_MATRIX: [[f64; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        m: IDENTITY_MATRIX,
+        inv: IDENTITY_MATRIX,
+    };
+
+    // Builds a transform from a row-major matrix whose bottom row is (0, 0, 0, 1).
+    // Returns None if the matrix can't be inverted.
+    pub fn from_matrix(m: [[f64; 4]; 4]) -> Option<Self> {
+        let rows = [0, 1, 2].map(|r| Vec3::new(m[r][0], m[r][1], m[r][2]));
+        let det = dot(&rows[0], &cross(&rows[1], &rows[2]));
+        if det.abs() < 1e-12 || !det.is_finite() {
+            return None;
+        }
+
+        // The inverse of the linear part is the transposed cofactor matrix over the
+        // determinant; the inverse translation undoes the original one.
+        let cofactors = [
+            cross(&rows[1], &rows[2]),
+            cross(&rows[2], &rows[0]),
+            cross(&rows[0], &rows[1]),
+        ];
+        let translation = Vec3::new(m[0][3], m[1][3], m[2][3]);
+        let mut inv = IDENTITY_MATRIX;
+        for (r, row) in inv.iter_mut().take(3).enumerate() {
+            let component = |v: &Vec3| [v.x(), v.y(), v.z()][r] / det;
+            let linear = cofactors.each_ref().map(component);
+            row[..3].copy_from_slice(&linear);
+            row[3] = -dot(&Vec3::new(linear[0], linear[1], linear[2]), &translation);
+        }
+
+        Some(Self { m, inv })
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut m = IDENTITY_MATRIX;
+        let mut inv = IDENTITY_MATRIX;
+        for (axis, value) in [offset.x(), offset.y(), offset.z()].into_iter().enumerate() {
+            m[axis][3] = value;
+            inv[axis][3] = -value;
+        }
+        Self { m, inv }
+    }
+
+    // Scales along each axis. Returns None if a factor is zero, like `from_matrix` does
+    // for any matrix that can't be inverted.
+    pub fn scale(factors: Vec3) -> Option<Self> {
+        let det = factors.x() * factors.y() * factors.z();
+        if det.abs() < 1e-12 || !det.is_finite() {
+            return None;
+        }
+
+        let mut m = IDENTITY_MATRIX;
+        let mut inv = IDENTITY_MATRIX;
+        for (axis, value) in [factors.x(), factors.y(), factors.z()]
+            .into_iter()
+            .enumerate()
+        {
+            m[axis][axis] = value;
+            inv[axis][axis] = 1.0 / value;
+        }
+        Some(Self { m, inv })
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    // Counterclockwise rotation about `axis` when looking down it towards the origin.
+    pub fn rotate(axis: &Vec3, degrees: f64) -> Self {
+        let a = Vec3::unit_vector(axis);
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        let m = [
+            [
+                cos + x * x * (1.0 - cos),
+                x * y * (1.0 - cos) - z * sin,
+                x * z * (1.0 - cos) + y * sin,
+                0.0,
+            ],
+            [
+                y * x * (1.0 - cos) + z * sin,
+                cos + y * y * (1.0 - cos),
+                y * z * (1.0 - cos) - x * sin,
+                0.0,
+            ],
+            [
+                z * x * (1.0 - cos) - y * sin,
+                z * y * (1.0 - cos) + x * sin,
+                cos + z * z * (1.0 - cos),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // Rotations are orthogonal, so the inverse is the transpose.
+        Self {
+            m,
+            inv: transpose(&m),
+        }
+    }
+
+    // The transform that applies `self` first and then `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            m: multiply(&next.m, &self.m),
+            inv: multiply(&self.inv, &next.inv),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn matrix(&self) -> [[f64; 4]; 4] {
+        self.m
+    }
+
+    // Negative for transforms that mirror space, which flips triangle winding.
+    pub fn determinant(&self) -> f64 {
+        let row = |r: usize| Vec3::new(self.m[r][0], self.m[r][1], self.m[r][2]);
+        dot(&row(0), &cross(&row(1), &row(2)))
+    }
+
+    pub fn apply_point(&self, p: &Point3) -> Point3 {
+        apply(&self.m, p, 1.0)
+    }
+
+    pub fn apply_vector(&self, v: &Vec3) -> Vec3 {
+        apply(&self.m, v, 0.0)
+    }
+
+    // Normals transform by the inverse transpose so they stay perpendicular to the
+    // transformed surface. The result is a unit vector.
+    pub fn apply_normal(&self, n: &Vec3) -> Vec3 {
+        let inv_t = transpose(&self.inv);
+        Vec3::unit_vector(&apply(&inv_t, n, 0.0))
+    }
+
+    // The direction is not normalized, so hit distances along the ray are unchanged.
+    pub fn apply_ray(&self, r: &Ray) -> Ray {
+        Ray::with_time(
+            self.apply_point(&r.origin()),
+            self.apply_vector(&r.direction()),
+            r.time(),
+        )
+    }
+
+    // Bounds the transformed corners of `bbox`.
+    pub fn apply_box(&self, bbox: &Aabb) -> Aabb {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+        for x in [bbox.x.min, bbox.x.max] {
+            for y in [bbox.y.min, bbox.y.max] {
+                for z in [bbox.z.min, bbox.z.max] {
+                    let p = self.apply_point(&Point3::new(x, y, z));
+                    min = Point3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z()));
+                    max = Point3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z()));
+                }
+            }
+        }
+        Aabb::from_points(min, max)
+    }
+}
+
+fn apply(m: &[[f64; 4]; 4], v: &Vec3, w: f64) -> Vec3 {
+    let row = |r: usize| m[r][0] * v.x() + m[r][1] * v.y() + m[r][2] * v.z() + m[r][3] * w;
+    Vec3::new(row(0), row(1), row(2))
+}
+
+fn multiply(a: &[[f64; 4]; 4], b: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut m = [[0.0; 4]; 4];
+    for (r, row) in m.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[r][k] * b[k][c]).sum();
+        }
+    }
+    m
+}
+
+fn transpose(m: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut t = [[0.0; 4]; 4];
+    for (r, row) in t.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = m[c][r];
+        }
+    }
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_rejects_singular_factors() {
+        for factors in [
+            Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(2.0, -0.0, 3.0),
+            Vec3::new(1.0, 1.0, f64::INFINITY),
+            Vec3::new(1.0, f64::NAN, 1.0),
+        ] {
+            assert_eq!(Transform::scale(factors), None, "{:?}", factors);
+        }
+    }
+
+    #[test]
+    fn scale_inverts() {
+        let scale = Transform::scale(Vec3::new(2.0, -0.5, 4.0)).unwrap();
+        let p = Point3::new(1.0, 2.0, 3.0);
+        assert!((scale.apply_point(&p) - Point3::new(2.0, -1.0, 12.0)).near_zero());
+        assert!((scale.inverse().apply_point(&scale.apply_point(&p)) - p).near_zero());
+        assert_eq!(scale.determinant(), -4.0);
+    }
+}
@@ -11,6 +11,12 @@ pub fn random_double_range(min: f64, max: f64) -> f64 {
     rng.random_range(min..max)
 }
 
+// Returns a random integer in [min, max].
+pub fn random_int(min: i32, max: i32) -> i32 {
+    let mut rng = rand::rng();
+    rng.random_range(min..=max)
+}
+
 pub fn degrees_to_radians(x: f64) -> f64 {
     x * PI / 180.0
 }
@@ -39,6 +39,19 @@ impl Vec3 {
         }
     }
 
+    pub fn random_in_unit_disk() -> Vec3 {
+        loop {
+            let p = Vec3::new(
+                utility::random_double_range(-1.0, 1.0),
+                utility::random_double_range(-1.0, 1.0),
+                0.0,
+            );
+            if p.length_squared() < 1.0 {
+                return p;
+            }
+        }
+    }
+
     pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
         let on_unit_sphere = Vec3::random_unit_vector();
 
@@ -93,6 +106,14 @@ pub fn dot(first: &Vec3, second: &Vec3) -> f64 {
     first.e[0] * second.e[0] + first.e[1] * second.e[1] + first.e[2] * second.e[2]
 }
 
+pub fn cross(u: &Vec3, v: &Vec3) -> Vec3 {
+    Vec3::new(
+        u.e[1] * v.e[2] - u.e[2] * v.e[1],
+        u.e[2] * v.e[0] - u.e[0] * v.e[2],
+        u.e[0] * v.e[1] - u.e[1] * v.e[0],
+    )
+}
+
 pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     let neg_uv = -uv;
     let cos_theta = dot(&neg_uv, n).min(1.0);